@@ -0,0 +1,37 @@
+//! Verification times against the baseline in `perf-baseline.json`: fails if any is more
+//! than 10% slower, or records the baseline with `-- --save`. Run `make wasm` first.
+//!
+//! cargo bench --features wasm_verify,prover --bench baseline [-- --save]
+
+use halo2_proofs::pasta::pallas;
+
+use wasm_verifier_arithmetic::{
+    expected_instances, host::baseline::Baseline, prover::Prover, Proof,
+};
+
+const PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/perf-baseline.json");
+const TOLERANCE: f64 = 0.1;
+
+fn main() {
+    let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+    let prover = Prover::build(4).unwrap();
+    let vk = prover.verifying_key();
+    let proof = Proof::new(include_bytes!("../proof.bin").to_vec());
+    let instances = expected_instances(pallas::Base::from(69), pallas::Base::from(42));
+    let current = Baseline::measure(wasm_bytes, &vk, &proof, &instances, 20).unwrap();
+
+    if std::env::args().any(|arg| arg == "--save") {
+        std::fs::write(PATH, current.to_json()).unwrap();
+        println!("saved {}", current.to_json());
+        return;
+    }
+    let baseline = std::fs::read_to_string(PATH).expect("no baseline, run with -- --save");
+    let baseline = Baseline::from_json(&baseline).expect("malformed baseline");
+    if let Err(regressions) = baseline.compare(&current, TOLERANCE) {
+        for regression in &regressions {
+            eprintln!("regression: {}", regression);
+        }
+        std::process::exit(1);
+    }
+    println!("no regressions: {}", current.to_json());
+}
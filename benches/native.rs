@@ -0,0 +1,34 @@
+//! Native proving, keygen and verification at K = 4.
+//!
+//! cargo bench --features prover --bench native
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use halo2_proofs::pasta::pallas;
+use rand::rngs::OsRng;
+
+use wasm_verifier_arithmetic::prover::{ArithWitness, Prover};
+
+fn native(c: &mut Criterion) {
+    let prover = Prover::build(4).unwrap();
+    let vk = prover.verifying_key();
+    let witness = ArithWitness {
+        a: pallas::Base::from(69),
+        b: pallas::Base::from(42),
+    };
+    let instances = witness.expected_instances();
+    let proof = prover.prove(&witness, &instances, OsRng).unwrap();
+    println!("proof size: {} bytes", proof.as_ref().len());
+
+    let mut group = c.benchmark_group("native");
+    group.bench_function("keygen", |b| b.iter(|| Prover::build(4).unwrap()));
+    group.bench_function("prove", |b| {
+        b.iter(|| prover.prove(&witness, &instances, OsRng).unwrap())
+    });
+    group.bench_function("verify", |b| {
+        b.iter(|| proof.verify(&vk, &[&instances]).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, native);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+//! Proof size and verification time over k, natively and in the guest, as CSV on stdout.
+//! Run `make wasm` first.
+//!
+//! cargo bench --features wasm_verify,prover --bench sweep > sweep.csv
+
+use wasm_verifier_arithmetic::host::bench::{sweep, sweep_csv};
+
+fn main() {
+    let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+    let rows = sweep(wasm_bytes, 4..=10).unwrap();
+    print!("{}", sweep_csv(&rows));
+}
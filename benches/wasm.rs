@@ -0,0 +1,59 @@
+//! The guest module under every available wasmer compiler: compilation, instantiation,
+//! keygen and verification of the proof.bin fixture. Run `make wasm` first.
+//!
+//! cargo bench --features wasm_verify --bench wasm
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use halo2_proofs::pasta::pallas;
+
+use wasm_verifier_arithmetic::{
+    encode_instances, expected_instances,
+    host::{CompiledModule, Compiler, HostConfig},
+};
+
+fn wasm(c: &mut Criterion) {
+    let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+    let proof = include_bytes!("../proof.bin");
+    let instances = encode_instances(&expected_instances(
+        pallas::Base::from(69),
+        pallas::Base::from(42),
+    ));
+
+    let mut group = c.benchmark_group("wasm");
+    // Singlepass compilation and in-wasm keygen take long enough that the default 100
+    // samples would run for minutes
+    group.sample_size(10);
+    for compiler in Compiler::available() {
+        let config = HostConfig {
+            compiler,
+            ..Default::default()
+        };
+
+        group.bench_function(format!("compile/{}", compiler), |b| {
+            b.iter(|| CompiledModule::new(wasm_bytes, config.clone()).unwrap())
+        });
+
+        let compiled = CompiledModule::new(wasm_bytes, config).unwrap();
+        group.bench_function(format!("instantiate/{}", compiler), |b| {
+            b.iter(|| compiled.instantiate().unwrap())
+        });
+        group.bench_function(format!("keygen/{}", compiler), |b| {
+            b.iter_batched(
+                || compiled.instantiate().unwrap(),
+                |mut harness| harness.warmup().unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+
+        // Warmed, so only verification is measured
+        let mut harness = compiled.instantiate().unwrap();
+        harness.warmup().unwrap();
+        group.bench_function(format!("verify/{}", compiler), |b| {
+            b.iter(|| assert!(harness.verify(proof, &instances).unwrap().is_valid()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, wasm);
+criterion_main!(benches);
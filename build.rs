@@ -0,0 +1,131 @@
+// With the `aot` feature, compile the checked-in verifier module with the host's default
+// engine configuration (singlepass + metering) and embed the native artifact, so
+// `host::CompiledModule::precompiled` skips compilation at startup.
+//
+// With the `srs` feature, compute the checksums `srs::load` checks shared params files
+// against. Params::new is deterministic, so this is the expected hash for every k.
+//
+// With the `embedded_keys` feature, run keygen for the circuit natively and write the
+// serialized vk (params included) for the guest to embed instead of building it at runtime.
+//
+// With the `grpc` feature, generate the tonic service from proto/verifier.proto.
+
+#[cfg(feature = "embedded_keys")]
+extern crate halo2_build as halo2_proofs;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=wasm_verifier_arithmetic.wasm");
+    println!("cargo:rerun-if-changed=src/circuit.rs");
+    println!("cargo:rerun-if-changed=src/gadget");
+
+    #[cfg(feature = "aot")]
+    aot::compile();
+
+    #[cfg(feature = "srs")]
+    srs::checksums();
+
+    #[cfg(feature = "embedded_keys")]
+    keys::vk();
+
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/verifier.proto");
+        tonic_build::compile_protos("proto/verifier.proto").unwrap();
+    }
+}
+
+#[cfg(feature = "aot")]
+mod aot {
+    use std::{path::PathBuf, sync::Arc};
+
+    use middlewares_build::Metering;
+    use singlepass_build::Singlepass;
+    use wasmer_build::{wasmparser::Operator, CompilerConfig, Engine, Module};
+
+    pub fn compile() {
+        let wasm_bytes = std::fs::read("wasm_verifier_arithmetic.wasm")
+            .expect("run `make wasm` before building with the aot feature");
+
+        // Must mirror host::build_engine for the default HostConfig, the gas limit itself
+        // is reset before every call so its initial value doesn't matter
+        let mut compiler_config = Singlepass::new();
+        let metering = Arc::new(Metering::new(0, |_: &Operator| -> u64 { 1 }));
+        compiler_config.push_middleware(metering);
+        let engine: Engine = compiler_config.into();
+
+        let module = Module::new(&engine, wasm_bytes).unwrap();
+        let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+        module
+            .serialize_to_file(out_dir.join("verifier.wasmu"))
+            .unwrap();
+    }
+}
+
+#[cfg(feature = "srs")]
+mod srs {
+    use std::path::PathBuf;
+
+    use halo2_build::{pasta::vesta, poly::commitment::Params};
+
+    // Largest k with an embedded checksum, keeping the build reasonably fast
+    const MAX_K: u32 = 11;
+
+    pub fn checksums() {
+        let mut out = String::from("pub const EXPECTED_CHECKSUMS: &[(u32, [u8; 32])] = &[\n");
+        for k in 1..=MAX_K {
+            // Must mirror params::to_bytes
+            let mut bytes = b"WVPR".to_vec();
+            bytes.push(1);
+            bytes.extend_from_slice(&k.to_le_bytes());
+            Params::<vesta::Affine>::new(k).write(&mut bytes).unwrap();
+
+            let hash = blake2b_build::Params::new()
+                .hash_length(32)
+                .personal(b"WasmVerifierSRS_")
+                .hash(&bytes);
+            out += &format!("    ({}, {:?}),\n", k, hash.as_bytes());
+        }
+        out += "];\n";
+
+        let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+        std::fs::write(out_dir.join("srs_checksums.rs"), out).unwrap();
+    }
+}
+
+#[cfg(feature = "embedded_keys")]
+#[allow(dead_code)]
+#[path = "src/gadget/mod.rs"]
+mod gadget;
+
+#[cfg(feature = "embedded_keys")]
+#[allow(dead_code)]
+#[path = "src/circuit.rs"]
+mod circuit;
+
+#[cfg(feature = "embedded_keys")]
+mod keys {
+    use std::path::PathBuf;
+
+    use halo2_build::{pasta::vesta, plonk, poly::commitment::Params};
+
+    use crate::circuit::MyCircuit;
+
+    // Must match abi::K
+    const K: u32 = 4;
+
+    pub fn vk() {
+        let params = Params::<vesta::Affine>::new(K);
+        let vk = plonk::keygen_vk(&params, &MyCircuit::default()).unwrap();
+
+        // Must mirror VerifyingKey::write
+        let mut bytes = b"WVVK".to_vec();
+        bytes.push(1);
+        bytes.extend_from_slice(&K.to_le_bytes());
+        params.write(&mut bytes).unwrap();
+        vk.write(&mut bytes).unwrap();
+
+        let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+        std::fs::write(out_dir.join("vk.bin"), bytes).unwrap();
+    }
+}
@@ -0,0 +1,33 @@
+/* C declarations for the `ffi` feature, see src/ffi.rs.
+ *
+ * cargo build --release --features ffi
+ * links as libwasm_verifier_arithmetic.{so,dylib} or wasm_verifier_arithmetic.dll
+ */
+
+#ifndef WASM_VERIFIER_H
+#define WASM_VERIFIER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define WV_STATUS_VALID 0
+#define WV_STATUS_INVALID 1
+#define WV_STATUS_MALFORMED 2
+#define WV_STATUS_ERROR 3
+
+typedef struct WvVerifyingKey WvVerifyingKey;
+
+int32_t wv_load_params(const uint8_t *ptr, size_t len);
+
+WvVerifyingKey *wv_vk_load(const uint8_t *ptr, size_t len);
+void wv_vk_free(WvVerifyingKey *vk);
+
+int32_t wv_verify_with(const WvVerifyingKey *vk,
+                       const uint8_t *proof_ptr, size_t proof_len,
+                       const uint8_t *inst_ptr, size_t inst_len);
+
+int32_t wv_verify(const uint8_t *proof_ptr, size_t proof_len,
+                  const uint8_t *vk_ptr, size_t vk_len,
+                  const uint8_t *inst_ptr, size_t inst_len);
+
+#endif
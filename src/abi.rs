@@ -0,0 +1,458 @@
+//! Memory-based guest ABI.
+//!
+//! The host copies the proof and instances into guest memory through `alloc`, calls
+//! `verify`, and reads back the result buffer whose pointer and length are packed into
+//! the returned u64. The host frees both inputs and the result with `dealloc`.
+//!
+//! Instances are encoded with `encode_instances`.
+//! The result buffer is a status byte followed by a UTF-8 message.
+//!
+//! Before the first verification the host may hand the guest params serialized with
+//! `params::to_bytes` through `load_params`, otherwise the guest generates them itself
+//! with `Params::new`.
+//!
+//! A module built for several circuits verifies each through `verify_circuit`, which takes
+//! the circuit id as an extra leading buffer. Each circuit's vk is built the first time a
+//! proof for it arrives and kept for the rest of the instance's lifetime.
+//!
+//! A vk generated by other halo2 tooling can likewise be handed over in halo2's bare
+//! encoding through `load_vk`, and is then used instead of the built-in one.
+
+use halo2_proofs::{pasta::vesta, poly::commitment::Params};
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use crate::{params, verify_bytes, Error, MyCircuit, VerifyingKey};
+
+const K: u32 = 4;
+
+/// Name of the custom section holding the circuit id hosts route requests by
+pub const CIRCUIT_ID_SECTION: &str = "wv_circuit_id";
+
+pub const CIRCUIT_ID: &str = "arith";
+
+#[cfg(target_arch = "wasm32")]
+#[link_section = "wv_circuit_id"]
+#[used]
+static CIRCUIT_ID_METADATA: [u8; CIRCUIT_ID.len()] = *b"arith";
+
+pub const STATUS_VALID: u8 = 0;
+pub const STATUS_INVALID: u8 = 1;
+pub const STATUS_MALFORMED: u8 = 2;
+pub const STATUS_ERROR: u8 = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Valid,
+    /// The proof did not verify against the instances
+    Invalid(String),
+    /// The inputs could not be decoded
+    Malformed(String),
+    /// The guest could not verify at all, e.g. keygen failed
+    Error(String),
+}
+
+impl Verdict {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Verdict::Valid)
+    }
+
+    /// One of the `STATUS_` constants
+    pub fn status(&self) -> u8 {
+        match self {
+            Verdict::Valid => STATUS_VALID,
+            Verdict::Invalid(_) => STATUS_INVALID,
+            Verdict::Malformed(_) => STATUS_MALFORMED,
+            Verdict::Error(_) => STATUS_ERROR,
+        }
+    }
+
+    /// What a verification's outcome means for the caller: bad instances are malformed
+    /// input, anything else wrong with the proof makes it invalid
+    pub fn of(result: crate::Result<()>) -> Self {
+        match result {
+            Ok(()) => Verdict::Valid,
+            Err(
+                e @ (Error::Instance(_)
+                | Error::InstanceColumns { .. }
+                | Error::InstanceCount { .. }),
+            ) => Verdict::Malformed(message(e)),
+            Err(e) => Verdict::Invalid(message(e)),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let message = match self {
+            Verdict::Valid => "",
+            Verdict::Invalid(msg) | Verdict::Malformed(msg) | Verdict::Error(msg) => msg.as_str(),
+        };
+        let mut bytes = Vec::with_capacity(1 + message.len());
+        bytes.push(self.status());
+        bytes.extend_from_slice(message.as_bytes());
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (status, message) = bytes.split_first()?;
+        let message = String::from_utf8(message.to_vec()).ok()?;
+        match *status {
+            STATUS_VALID => Some(Verdict::Valid),
+            STATUS_INVALID => Some(Verdict::Invalid(message)),
+            STATUS_MALFORMED => Some(Verdict::Malformed(message)),
+            STATUS_ERROR => Some(Verdict::Error(message)),
+            _ => None,
+        }
+    }
+}
+
+/// The message a verdict carries for `e`. Empty with `terse_errors`, which leaves the
+/// error formatting code out of the module.
+fn message(e: impl core::fmt::Display) -> String {
+    if cfg!(feature = "terse_errors") {
+        String::new()
+    } else {
+        e.to_string()
+    }
+}
+
+pub fn pack(ptr: u32, len: u32) -> u64 {
+    ((ptr as u64) << 32) | len as u64
+}
+
+pub fn unpack(packed: u64) -> (u32, u32) {
+    ((packed >> 32) as u32, packed as u32)
+}
+
+/// The vk, built on first use and kept for the lifetime of the instance. Hosts can call
+/// `build_vk` once and snapshot the warmed instance to skip keygen everywhere else.
+#[cfg(target_arch = "wasm32")]
+fn cached_vk() -> Result<&'static VerifyingKey, &'static str> {
+    // The module's own circuit is always known
+    cached_vk_for(CIRCUIT_ID).unwrap()
+}
+
+/// Keys by circuit id, each built on first use. Leaked so they can be handed out for the
+/// lifetime of the instance, which is as long as they would be kept anyway. Keygen is
+/// deterministic, so a failure is remembered rather than retried.
+static VKS: Mutex<BTreeMap<&'static str, Result<&'static VerifyingKey, &'static str>>> =
+    Mutex::new(BTreeMap::new());
+
+/// The vk for `circuit_id`, or None if this module has no such circuit
+fn cached_vk_for(circuit_id: &str) -> Option<Result<&'static VerifyingKey, &'static str>> {
+    if circuit_id == CIRCUIT_ID {
+        if let Some(vk) = EXTERNAL_VK.get() {
+            return Some(Ok(vk));
+        }
+    }
+
+    let mut vks = VKS.lock().unwrap();
+    if let Some(vk) = vks.get(circuit_id) {
+        return Some(*vk);
+    }
+    let (circuit_id, vk) = keygen(circuit_id)?;
+    let vk = match vk {
+        Ok(vk) => Ok(&*Box::leak(Box::new(preallocated(vk)))),
+        Err(e) => Err(&*e.leak()),
+    };
+    vks.insert(circuit_id, vk);
+    Some(vk)
+}
+
+/// Build the vk for one of the module's circuits. A module verifying several circuits
+/// adds an arm per circuit id.
+fn keygen(circuit_id: &str) -> Option<(&'static str, Result<VerifyingKey, String>)> {
+    match circuit_id {
+        CIRCUIT_ID => {
+            let vk = match INJECTED_PARAMS.get() {
+                Some((k, params)) => {
+                    VerifyingKey::build_with_params(*k, params.clone(), &MyCircuit::default())
+                }
+                #[cfg(feature = "embedded_keys")]
+                None => VerifyingKey::embedded().map_err(Into::into),
+                #[cfg(not(feature = "embedded_keys"))]
+                None => VerifyingKey::build(K, &MyCircuit::default()),
+            };
+            let vk = vk
+                .map(|vk| vk.with_instance_lengths(MyCircuit::INSTANCE_LENGTHS.to_vec()))
+                .map_err(message);
+            Some((CIRCUIT_ID, vk))
+        }
+        _ => None,
+    }
+}
+
+/// Grow the heap for verifying against `vk` once it is known. The arena features size
+/// their arena instead.
+fn preallocated(vk: VerifyingKey) -> VerifyingKey {
+    #[cfg(all(
+        target_arch = "wasm32",
+        not(any(feature = "bounded_alloc", feature = "arena"))
+    ))]
+    crate::bounded::preallocate(crate::bounded::scratch_bytes(&vk));
+    vk
+}
+
+static INJECTED_PARAMS: OnceLock<(u32, Arc<Params<vesta::Affine>>)> = OnceLock::new();
+
+/// Deserialize host-supplied params for the vk to use. Params larger than `K` make the
+/// guest run keygen at their size instead, e.g. to measure how verification scales.
+/// Fails if they don't parse, are too small for the circuit, or if params were already
+/// loaded.
+pub fn load_params_bytes(bytes: &[u8]) -> Result<(), String> {
+    let (k, params) = params::from_bytes(bytes).map_err(|e| format!("invalid params: {}", e))?;
+    if k < K {
+        return Err(format!(
+            "params are for k = {}, the circuit needs at least {}",
+            k, K
+        ));
+    }
+    INJECTED_PARAMS
+        .set((k, Arc::new(params)))
+        .map_err(|_| "params already loaded".to_string())
+}
+
+static EXTERNAL_VK: OnceLock<VerifyingKey> = OnceLock::new();
+
+/// Deserialize a host-supplied vk in halo2's bare encoding, on the injected params if any.
+/// Fails if it doesn't parse against the circuit or a vk was already loaded.
+pub fn load_vk_bytes(bytes: &[u8]) -> Result<(), String> {
+    let params = INJECTED_PARAMS
+        .get()
+        .map(|(_, params)| params.clone())
+        .unwrap_or_else(|| params::shared(K));
+    let vk = VerifyingKey::from_halo2_bytes(bytes, params, MyCircuit::default())
+        .map_err(|e| format!("invalid verifying key: {}", e))?
+        .with_instance_lengths(MyCircuit::INSTANCE_LENGTHS.to_vec());
+    EXTERNAL_VK
+        .set(preallocated(vk))
+        .map_err(|_| "verifying key already loaded".to_string())
+}
+
+/// The work behind the `verify` export, on buffers already copied out of guest memory
+pub fn verify_buffers(proof: &[u8], instances: &[u8]) -> Verdict {
+    verify_circuit_buffers(CIRCUIT_ID, proof, instances)
+}
+
+/// The work behind the `verify_circuit` export: `verify_buffers` for the named circuit
+pub fn verify_circuit_buffers(circuit_id: &str, proof: &[u8], instances: &[u8]) -> Verdict {
+    let vk = match cached_vk_for(circuit_id) {
+        Some(Ok(vk)) => vk,
+        Some(Err(msg)) => return Verdict::Error(msg.to_string()),
+        None => return Verdict::Malformed(format!("unknown circuit {:?}", circuit_id)),
+    };
+    Verdict::of(verify_bytes(vk, proof, instances))
+}
+
+/// The prover, built on first use like the vk
+#[cfg(feature = "prover")]
+fn cached_prover() -> Result<&'static crate::prover::Prover, &'static str> {
+    static PROVER: OnceLock<Result<crate::prover::Prover, String>> = OnceLock::new();
+    let prover = PROVER.get_or_init(|| crate::prover::Prover::build(K).map_err(message));
+    prover.as_ref().map_err(|e| e.as_str())
+}
+
+/// The work behind the `prove` export. The witness is encoded for `ArithWitness::from_bytes`,
+/// the seed 32 bytes for the proving RNG. Returns `STATUS_VALID` followed by the proof, or
+/// an encoded failure `Verdict`.
+#[cfg(feature = "prover")]
+pub fn prove_buffers(witness: &[u8], instances: &[u8], seed: &[u8]) -> Vec<u8> {
+    let malformed = |msg: String| Verdict::Malformed(msg).encode();
+    let witness = match crate::prover::ArithWitness::from_bytes(witness) {
+        Ok(witness) => witness,
+        Err(e) => return malformed(message(e)),
+    };
+    let instances = match crate::decode_instances(instances) {
+        Ok(instances) => instances,
+        Err(e) => return malformed(message(e)),
+    };
+    let Ok(seed) = <[u8; 32]>::try_from(seed) else {
+        return malformed("seed must be 32 bytes".to_string());
+    };
+
+    let prover = match cached_prover() {
+        Ok(prover) => prover,
+        Err(msg) => return Verdict::Error(msg.to_string()).encode(),
+    };
+    match prover.prove_deterministic(&witness, &instances, seed) {
+        Ok(proof) => {
+            let mut bytes = vec![STATUS_VALID];
+            bytes.extend_from_slice(proof.as_ref());
+            bytes
+        }
+        Err(e) => Verdict::Error(message(e)).encode(),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn build_vk() {
+    // A failure is reported by the next `verify`
+    let _ = cached_vk();
+}
+
+/// Returns `STATUS_VALID` when the params were accepted, `STATUS_MALFORMED` otherwise
+///
+/// # Safety
+///
+/// The buffer must have been written by the host into memory obtained from `alloc`.
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub unsafe extern "C" fn load_params(ptr: *const u8, len: u32) -> u32 {
+    let bytes = core::slice::from_raw_parts(ptr, len as usize);
+    match load_params_bytes(bytes) {
+        Ok(()) => STATUS_VALID as u32,
+        Err(_) => STATUS_MALFORMED as u32,
+    }
+}
+
+/// Returns `STATUS_VALID` when the vk was accepted, `STATUS_MALFORMED` otherwise
+///
+/// # Safety
+///
+/// The buffer must have been written by the host into memory obtained from `alloc`.
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub unsafe extern "C" fn load_vk(ptr: *const u8, len: u32) -> u32 {
+    let bytes = core::slice::from_raw_parts(ptr, len as usize);
+    match load_vk_bytes(bytes) {
+        Ok(()) => STATUS_VALID as u32,
+        Err(_) => STATUS_MALFORMED as u32,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub extern "C" fn alloc(len: u32) -> *mut u8 {
+    let mut buf = Vec::<u8>::with_capacity(len as usize);
+    let ptr = buf.as_mut_ptr();
+    core::mem::forget(buf);
+    ptr
+}
+
+/// # Safety
+///
+/// `ptr` and `len` must come from a previous `alloc` or `verify` result.
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub unsafe extern "C" fn dealloc(ptr: *mut u8, len: u32) {
+    drop(Vec::from_raw_parts(ptr, 0, len as usize));
+}
+
+/// Result buffer as for `verify`, see `prove_buffers` for its contents
+///
+/// # Safety
+///
+/// All buffers must have been written by the host into memory obtained from `alloc`.
+#[cfg(all(target_arch = "wasm32", feature = "prover"))]
+#[no_mangle]
+pub unsafe extern "C" fn prove(
+    witness_ptr: *const u8,
+    witness_len: u32,
+    instances_ptr: *const u8,
+    instances_len: u32,
+    seed_ptr: *const u8,
+    seed_len: u32,
+) -> u64 {
+    let witness = core::slice::from_raw_parts(witness_ptr, witness_len as usize);
+    let instances = core::slice::from_raw_parts(instances_ptr, instances_len as usize);
+    let seed = core::slice::from_raw_parts(seed_ptr, seed_len as usize);
+
+    let result = prove_buffers(witness, instances, seed).into_boxed_slice();
+    let len = result.len() as u32;
+    let ptr = Box::into_raw(result) as *mut u8;
+    pack(ptr as u32, len)
+}
+
+/// # Safety
+///
+/// Both buffers must have been written by the host into memory obtained from `alloc`.
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub unsafe extern "C" fn verify(
+    proof_ptr: *const u8,
+    proof_len: u32,
+    instances_ptr: *const u8,
+    instances_len: u32,
+) -> u64 {
+    let proof = core::slice::from_raw_parts(proof_ptr, proof_len as usize);
+    let instances = core::slice::from_raw_parts(instances_ptr, instances_len as usize);
+
+    #[cfg(not(any(feature = "bounded_alloc", feature = "arena")))]
+    let verdict = verify_buffers(proof, instances);
+    #[cfg(feature = "bounded_alloc")]
+    let verdict = verify_bounded(proof, instances);
+    #[cfg(all(feature = "arena", not(feature = "bounded_alloc")))]
+    let verdict = verify_in_arena(proof, instances);
+
+    let result = verdict.encode().into_boxed_slice();
+    let len = result.len() as u32;
+    let ptr = Box::into_raw(result) as *mut u8;
+    pack(ptr as u32, len)
+}
+
+/// Like `verify`, for the circuit whose UTF-8 id is in the first buffer
+///
+/// # Safety
+///
+/// All buffers must have been written by the host into memory obtained from `alloc`.
+#[cfg(target_arch = "wasm32")]
+#[no_mangle]
+pub unsafe extern "C" fn verify_circuit(
+    id_ptr: *const u8,
+    id_len: u32,
+    proof_ptr: *const u8,
+    proof_len: u32,
+    instances_ptr: *const u8,
+    instances_len: u32,
+) -> u64 {
+    let circuit_id = core::slice::from_raw_parts(id_ptr, id_len as usize);
+    let proof = core::slice::from_raw_parts(proof_ptr, proof_len as usize);
+    let instances = core::slice::from_raw_parts(instances_ptr, instances_len as usize);
+
+    let verdict = match core::str::from_utf8(circuit_id) {
+        Ok(circuit_id) => verify_circuit_buffers(circuit_id, proof, instances),
+        Err(_) => Verdict::Malformed("circuit id is not UTF-8".to_string()),
+    };
+    let result = verdict.encode().into_boxed_slice();
+    let len = result.len() as u32;
+    let ptr = Box::into_raw(result) as *mut u8;
+    pack(ptr as u32, len)
+}
+
+/// `verify_buffers` without heap allocation past the arena reserved for the vk
+#[cfg(all(target_arch = "wasm32", feature = "bounded_alloc"))]
+fn verify_bounded(proof: &[u8], instances: &[u8]) -> Verdict {
+    use crate::bounded;
+
+    // Build the vk and measure its proofs before the arena is on, they outlive it
+    let vk = match cached_vk() {
+        Ok(vk) => vk,
+        Err(msg) => return Verdict::Error(msg.to_string()),
+    };
+    if !bounded::reserve(bounded::scratch_bytes(vk)) {
+        return Verdict::Error("could not reserve the verification arena".to_string());
+    }
+    bounded::with_arena(|| verify_buffers(proof, instances))
+}
+
+/// `verify_buffers` with its temporaries bumped out of an arena where they fit
+#[cfg(all(
+    target_arch = "wasm32",
+    feature = "arena",
+    not(feature = "bounded_alloc")
+))]
+fn verify_in_arena(proof: &[u8], instances: &[u8]) -> Verdict {
+    use crate::bounded;
+
+    // As in verify_bounded, the vk outlives the arena
+    let vk = match cached_vk() {
+        Ok(vk) => vk,
+        Err(msg) => return Verdict::Error(msg.to_string()),
+    };
+    // Without an arena everything falls back to the heap, so failing to reserve is fine
+    bounded::reserve(bounded::scratch_bytes(vk));
+    bounded::with_arena_or_heap(|| verify_buffers(proof, instances))
+}
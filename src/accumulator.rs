@@ -0,0 +1,112 @@
+//! Deferred verification: every proof added to an `Accumulator` is checked up to its final
+//! MSM, and `finalize` checks a random linear combination of all of those MSMs at once.
+//!
+//! Unlike `batch::BatchVerifier` this needs no OS randomness, so it also runs in the guest.
+//! The combination coefficients are derived from a hash of every proof and its instances,
+//! which are all fixed by the time `finalize` squeezes them.
+
+use halo2_proofs::{
+    pasta::{group::ff::FromUniformBytes, pallas, vesta},
+    plonk::{self, VerificationStrategy},
+    poly::commitment::{Guard, MSM},
+};
+
+use crate::{encode_instances, ensure_consumed, Proof, VerifyingKey};
+
+/// Keeps the MSM a proof's verification ends with instead of evaluating it
+struct Deferred<'params, 'o> {
+    msm: MSM<'params, vesta::Affine>,
+    out: &'o mut Option<MSM<'params, vesta::Affine>>,
+}
+
+impl<'params> VerificationStrategy<'params, vesta::Affine> for Deferred<'params, '_> {
+    type Output = ();
+
+    fn process(
+        self,
+        f: impl FnOnce(
+            MSM<'params, vesta::Affine>,
+        ) -> Result<Guard<'params, vesta::Affine>, plonk::Error>,
+    ) -> Result<Self::Output, plonk::Error> {
+        let guard = f(self.msm)?;
+        *self.out = Some(guard.use_challenges());
+        Ok(())
+    }
+}
+
+pub struct Accumulator<'a> {
+    vk: &'a VerifyingKey,
+    msms: Vec<MSM<'a, vesta::Affine>>,
+    hasher: blake2b_simd::State,
+}
+
+impl<'a> Accumulator<'a> {
+    pub fn new(vk: &'a VerifyingKey) -> Self {
+        Accumulator {
+            vk,
+            msms: vec![],
+            hasher: blake2b_simd::Params::new()
+                .hash_length(64)
+                .personal(b"WasmVerifierAcc_")
+                .to_state(),
+        }
+    }
+
+    /// Run everything but the final MSM. Errors here mean the proof is malformed, while a
+    /// proof that is well-formed but invalid only shows when `finalize` returns false.
+    pub fn add(
+        &mut self,
+        proof: &Proof,
+        instances: &[&[pallas::Base]],
+    ) -> Result<(), plonk::Error> {
+        let vk = self.vk;
+        let mut out = None;
+        let strategy = Deferred {
+            msm: vk.params.empty_msm(),
+            out: &mut out,
+        };
+        let mut reader = proof.as_ref();
+        let mut transcript = crate::transcript::reader(&mut reader);
+        plonk::verify_proof(&vk.params, &vk.vk, strategy, &[instances], &mut transcript)?;
+        ensure_consumed(reader)?;
+
+        self.hasher
+            .update(&(proof.as_ref().len() as u64).to_le_bytes());
+        self.hasher.update(proof.as_ref());
+        self.hasher.update(&(instances.len() as u32).to_le_bytes());
+        for column in instances {
+            self.hasher.update(&encode_instances(column));
+        }
+        // process always fills it in when verify_proof succeeds
+        self.msms.push(out.unwrap());
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.msms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.msms.is_empty()
+    }
+
+    /// True if every added proof is valid. One MSM over all of them.
+    pub fn finalize(self) -> bool {
+        let seed = self.hasher.finalize();
+        let mut acc = self.vk.params.empty_msm();
+        for (i, mut msm) in self.msms.into_iter().enumerate() {
+            let coefficient = blake2b_simd::Params::new()
+                .hash_length(64)
+                .personal(b"WasmVerifierAcc_")
+                .to_state()
+                .update(seed.as_bytes())
+                .update(&(i as u64).to_le_bytes())
+                .finalize();
+            let coefficient =
+                pallas::Base::from_uniform_bytes(coefficient.as_bytes().try_into().unwrap());
+            msm.scale(coefficient);
+            acc.add_msm(&msm);
+        }
+        acc.eval()
+    }
+}
@@ -0,0 +1,99 @@
+//! Inspecting the randomness a proof was blinded with, for audit builds only.
+//!
+//! Every prover entrypoint already takes its RNG. `RecordingRng` wraps one and keeps a copy
+//! of everything it hands out. halo2 draws each blinding factor as a field element from 64
+//! uniform bytes, so those draws can be mapped back to the scalars that went into the proof.
+//!
+//! The recorded values are as secret as the witness: anyone holding them and the proof can
+//! recover the private inputs. Never enable `audit` in a build that proves real statements.
+
+use halo2_proofs::pasta::{group::ff::FromUniformBytes, pallas};
+use rand::{CryptoRng, RngCore};
+
+pub struct RecordingRng<R> {
+    inner: R,
+    draws: Vec<Vec<u8>>,
+}
+
+impl<R: RngCore> RecordingRng<R> {
+    pub fn new(inner: R) -> Self {
+        RecordingRng {
+            inner,
+            draws: vec![],
+        }
+    }
+
+    /// Every draw in order, as the bytes the caller received
+    pub fn draws(&self) -> &[Vec<u8>] {
+        &self.draws
+    }
+
+    /// The 64-byte draws as the field elements halo2 derived from them
+    pub fn blinding_factors(&self) -> Vec<pallas::Base> {
+        self.draws
+            .iter()
+            .filter_map(|draw| <&[u8; 64]>::try_from(draw.as_slice()).ok())
+            .map(pallas::Base::from_uniform_bytes)
+            .collect()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: RngCore> RngCore for RecordingRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        let x = self.inner.next_u32();
+        self.draws.push(x.to_le_bytes().to_vec());
+        x
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let x = self.inner.next_u64();
+        self.draws.push(x.to_le_bytes().to_vec());
+        x
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.inner.fill_bytes(dest);
+        self.draws.push(dest.to_vec());
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.inner.try_fill_bytes(dest)?;
+        self.draws.push(dest.to_vec());
+        Ok(())
+    }
+}
+
+impl<R: CryptoRng> CryptoRng for RecordingRng<R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expected_instances;
+    use crate::prover::{ArithWitness, Prover};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_recording_rng() {
+        let prover = Prover::build(4).unwrap();
+        let witness = ArithWitness {
+            a: pallas::Base::from(69),
+            b: pallas::Base::from(42),
+        };
+        let public_inputs = expected_instances(pallas::Base::from(69), pallas::Base::from(42));
+
+        let mut rng = RecordingRng::new(ChaCha20Rng::from_seed([7; 32]));
+        let proof = prover.prove(&witness, &public_inputs, &mut rng).unwrap();
+        assert!(!rng.blinding_factors().is_empty());
+
+        // Recording doesn't change what the prover sees
+        let seeded = prover
+            .prove_deterministic(&witness, &public_inputs, [7; 32])
+            .unwrap();
+        assert_eq!(proof.as_ref(), seeded.as_ref());
+    }
+}
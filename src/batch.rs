@@ -0,0 +1,72 @@
+//! Batch verification of many proofs against one vk, behind the `batch` feature.
+//!
+//! halo2's batch strategy folds every proof's final MSM into a single one with random
+//! coefficients, so the expensive multiexp runs once per batch instead of once per proof.
+//! The randomness comes from `OsRng`, which wasm32-unknown-unknown doesn't provide, so
+//! this is for native hosts only.
+//!
+//! halo2 doesn't report how much of each transcript the batch consumed, so unlike
+//! `Proof::verify` a batch does not reject proofs with trailing bytes.
+
+use halo2_proofs::{
+    pasta::{pallas, vesta},
+    plonk,
+};
+
+use crate::{Proof, VerifyingKey};
+
+pub struct BatchVerifier<'a> {
+    vk: &'a VerifyingKey,
+    inner: plonk::BatchVerifier<vesta::Affine>,
+    // Kept to find the culprits when the batch fails
+    items: Vec<(Proof, Vec<Vec<pallas::Base>>)>,
+}
+
+impl<'a> BatchVerifier<'a> {
+    pub fn new(vk: &'a VerifyingKey) -> Self {
+        BatchVerifier {
+            vk,
+            inner: plonk::BatchVerifier::new(),
+            items: vec![],
+        }
+    }
+
+    /// `instances` holds one slice per instance column, as for `Proof::verify`
+    pub fn add(&mut self, proof: &Proof, instances: &[&[pallas::Base]]) {
+        let instances: Vec<_> = instances.iter().map(|column| column.to_vec()).collect();
+        self.inner
+            .add_proof(vec![instances.clone()], proof.as_ref().to_vec());
+        self.items.push((proof.clone(), instances));
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// True if every proof in the batch is valid
+    pub fn finalize(self) -> bool {
+        self.inner.finalize(&self.vk.params, &self.vk.vk)
+    }
+
+    /// Like `finalize`, but on failure verify the proofs one by one and return the indices
+    /// of the invalid ones
+    pub fn finalize_or_find_invalid(self) -> Result<(), Vec<usize>> {
+        let BatchVerifier { vk, inner, items } = self;
+        if inner.finalize(&vk.params, &vk.vk) {
+            return Ok(());
+        }
+        Err(items
+            .iter()
+            .enumerate()
+            .filter(|(_, (proof, instances))| {
+                let instances: Vec<_> = instances.iter().map(Vec::as_slice).collect();
+                proof.verify(vk, &instances).is_err()
+            })
+            .map(|(i, _)| i)
+            .collect())
+    }
+}
@@ -0,0 +1,66 @@
+//! Verify (proof, instances) rows from a CSV or Parquet file through the batch verifier
+//! and write one result per row as CSV to stdout, see `bulk`.
+//!
+//! usage: wasm-verifier-bulk <rows.csv | rows.parquet> [vk.bin]
+//!
+//! Without a vk file, the arithmetic circuit's key comes from keygen. Exits with 0 when
+//! every row is valid, 1 when some are not and 2 on errors.
+
+use std::{io::BufReader, process::ExitCode};
+
+use wasm_verifier_arithmetic::bulk::{self, BulkError, Row};
+
+fn read_rows(path: &str) -> Result<Vec<Row>, BulkError> {
+    let file = std::fs::File::open(path)?;
+    if path.ends_with(".parquet") {
+        #[cfg(feature = "parquet")]
+        return bulk::read_parquet(file);
+        #[cfg(not(feature = "parquet"))]
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "built without the parquet feature",
+        )
+        .into());
+    }
+    bulk::read_csv(BufReader::new(file))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    if !(2..=3).contains(&args.len()) {
+        eprintln!("usage: {} <rows.csv | rows.parquet> [vk.bin]", args[0]);
+        return ExitCode::from(2);
+    }
+
+    let rows = match read_rows(&args[1]) {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("{}: {}", args[1], e);
+            return ExitCode::from(2);
+        }
+    };
+    let vk_bytes = match args.get(2).map(std::fs::read).transpose() {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{}: {}", args[2], e);
+            return ExitCode::from(2);
+        }
+    };
+    let vk = match bulk::arith_vk(vk_bytes.as_deref()) {
+        Ok(vk) => vk,
+        Err(e) => {
+            eprintln!("verifying key: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let verdicts = bulk::verify_rows(&vk, &rows, bulk::DEFAULT_CHUNK);
+    if let Err(e) = bulk::write_csv(std::io::stdout().lock(), &verdicts) {
+        eprintln!("{}", e);
+        return ExitCode::from(2);
+    }
+    match verdicts.iter().all(|verdict| verdict.is_valid()) {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::from(1),
+    }
+}
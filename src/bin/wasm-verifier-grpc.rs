@@ -0,0 +1,149 @@
+//! Verification as a gRPC service, see proto/verifier.proto. Indexers verifying many
+//! proofs a second keep one `VerifyStream` open and get verdicts back as they complete.
+//!
+//! usage: wasm-verifier-grpc <listen addr> <module.wasm>...
+
+use std::{net::SocketAddr, pin::Pin, sync::Arc};
+
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+use wasm_verifier_arithmetic::{
+    abi::Verdict,
+    host::{HostConfig, ModuleRegistry},
+};
+
+mod proto {
+    tonic::include_proto!("wasm_verifier");
+}
+
+use proto::verifier_server::{Verifier, VerifierServer};
+
+/// Verdicts remembered across requests, see ModuleRegistry::with_cache
+const CACHE_CAPACITY: usize = 4096;
+
+struct Service {
+    registry: Arc<ModuleRegistry>,
+    /// Verifications in flight over all streams, one per pooled instance
+    permits: Arc<Semaphore>,
+}
+
+fn verify_blocking(
+    registry: &ModuleRegistry,
+    request: proto::VerifyRequest,
+) -> proto::VerifyResponse {
+    let (status, message) =
+        match registry.verify(&request.circuit_id, &request.proof, &request.instances) {
+            Ok(Verdict::Valid) => (proto::Status::Valid, String::new()),
+            Ok(Verdict::Invalid(msg)) => (proto::Status::Invalid, msg),
+            Ok(Verdict::Malformed(msg)) => (proto::Status::Malformed, msg),
+            Ok(Verdict::Error(msg)) => (proto::Status::Error, msg),
+            Err(e) => (proto::Status::Error, e.to_string()),
+        };
+    proto::VerifyResponse {
+        status: status as i32,
+        message,
+        tag: request.tag,
+    }
+}
+
+impl Service {
+    async fn verify_one(
+        &self,
+        request: proto::VerifyRequest,
+    ) -> Result<proto::VerifyResponse, Status> {
+        let _permit = self.permits.clone().acquire_owned().await.unwrap();
+        let registry = self.registry.clone();
+        // Verification blocks in wasm, keep it off the async workers
+        tokio::task::spawn_blocking(move || verify_blocking(&registry, request))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+type ResponseStream = Pin<Box<dyn Stream<Item = Result<proto::VerifyResponse, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Verifier for Service {
+    async fn verify(
+        &self,
+        request: Request<proto::VerifyRequest>,
+    ) -> Result<Response<proto::VerifyResponse>, Status> {
+        let request = request.into_inner();
+        if self.registry.get(&request.circuit_id).is_none() {
+            return Err(Status::not_found(format!(
+                "no module registered for circuit {}",
+                request.circuit_id
+            )));
+        }
+        self.verify_one(request).await.map(Response::new)
+    }
+
+    type VerifyStreamStream = ResponseStream;
+
+    async fn verify_stream(
+        &self,
+        request: Request<Streaming<proto::VerifyRequest>>,
+    ) -> Result<Response<ResponseStream>, Status> {
+        let mut requests = request.into_inner();
+        let (tx, rx) = mpsc::channel(self.permits.available_permits().max(1) * 2);
+        let registry = self.registry.clone();
+        let permits = self.permits.clone();
+
+        tokio::spawn(async move {
+            while let Some(request) = requests.next().await {
+                let request = match request {
+                    Ok(request) => request,
+                    Err(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        break;
+                    }
+                };
+                // Waiting for a permit here, before reading the next request, is the
+                // backpressure on the client
+                let permit = permits.clone().acquire_owned().await.unwrap();
+                let registry = registry.clone();
+                let tx = tx.clone();
+                tokio::task::spawn_blocking(move || {
+                    let response = verify_blocking(&registry, request);
+                    drop(permit);
+                    let _ = tx.blocking_send(Ok(response));
+                });
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: {} <listen addr> <module.wasm>...", args[0]);
+        std::process::exit(2);
+    }
+    let addr: SocketAddr = args[1].parse().expect("invalid listen address");
+
+    let pool_size = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut registry = ModuleRegistry::with_cache(CACHE_CAPACITY);
+    for path in &args[2..] {
+        let wasm = std::fs::read(path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        let circuit_id = registry
+            .register(&wasm, pool_size, HostConfig::default())
+            .unwrap_or_else(|e| panic!("{}: {}", path, e));
+        eprintln!("{}: circuit {:?}", path, circuit_id);
+    }
+
+    let service = Service {
+        registry: Arc::new(registry),
+        permits: Arc::new(Semaphore::new(pool_size)),
+    };
+    eprintln!("listening on {}", addr);
+    Server::builder()
+        .add_service(VerifierServer::new(service))
+        .serve(addr)
+        .await
+        .unwrap();
+}
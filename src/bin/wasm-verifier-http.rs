@@ -0,0 +1,98 @@
+//! Verification as an HTTP sidecar: `POST /verify` routed to verifier wasm modules by
+//! circuit id.
+//!
+//! usage: wasm-verifier-http <listen addr> <module.wasm>...
+//!
+//! The request body is `{"circuit_id": "arith", "proof": "<hex>", "instances": "<hex>"}`,
+//! the instances encoded with `encode_instances` as in the guest ABI. The response is
+//! `{"status": "valid" | "invalid" | "malformed" | "error", "message": "..."}` with 200
+//! for a verdict on the proof, 400 for malformed input, 404 for an unknown circuit and
+//! 500 when the host fails. `GET /circuits` lists the registered circuit ids.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{extract::State, http::StatusCode, routing, Json, Router};
+use serde_json::{json, Value};
+
+use wasm_verifier_arithmetic::{
+    abi::Verdict,
+    hex,
+    host::{HostConfig, HostError, ModuleRegistry},
+};
+
+/// Verdicts remembered across requests, see ModuleRegistry::with_cache
+const CACHE_CAPACITY: usize = 4096;
+
+type Response = (StatusCode, Json<Value>);
+
+fn respond(code: StatusCode, status: &str, message: &str) -> Response {
+    (code, Json(json!({ "status": status, "message": message })))
+}
+
+fn field<'a>(body: &'a Value, name: &str) -> Result<&'a str, String> {
+    body.get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("missing string field {:?}", name))
+}
+
+async fn verify(State(registry): State<Arc<ModuleRegistry>>, Json(body): Json<Value>) -> Response {
+    let request = field(&body, "circuit_id").and_then(|circuit_id| {
+        let proof = hex::decode(field(&body, "proof")?).map_err(|e| format!("proof: {}", e))?;
+        let instances =
+            hex::decode(field(&body, "instances")?).map_err(|e| format!("instances: {}", e))?;
+        Ok((circuit_id.to_string(), proof, instances))
+    });
+    let (circuit_id, proof, instances) = match request {
+        Ok(request) => request,
+        Err(msg) => return respond(StatusCode::BAD_REQUEST, "malformed", &msg),
+    };
+
+    // Verification blocks in wasm, keep it off the async workers
+    let verdict =
+        tokio::task::spawn_blocking(move || registry.verify(&circuit_id, &proof, &instances)).await;
+    match verdict {
+        Ok(Ok(Verdict::Valid)) => respond(StatusCode::OK, "valid", ""),
+        Ok(Ok(Verdict::Invalid(msg))) => respond(StatusCode::OK, "invalid", &msg),
+        Ok(Ok(Verdict::Malformed(msg))) => respond(StatusCode::BAD_REQUEST, "malformed", &msg),
+        Ok(Ok(Verdict::Error(msg))) => respond(StatusCode::INTERNAL_SERVER_ERROR, "error", &msg),
+        Ok(Err(e @ HostError::UnknownCircuit(_))) => {
+            respond(StatusCode::NOT_FOUND, "malformed", &e.to_string())
+        }
+        Ok(Err(e)) => respond(StatusCode::INTERNAL_SERVER_ERROR, "error", &e.to_string()),
+        Err(e) => respond(StatusCode::INTERNAL_SERVER_ERROR, "error", &e.to_string()),
+    }
+}
+
+async fn circuits(State(registry): State<Arc<ModuleRegistry>>) -> Json<Value> {
+    let mut ids: Vec<&str> = registry.circuit_ids().collect();
+    ids.sort();
+    Json(json!(ids))
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        eprintln!("usage: {} <listen addr> <module.wasm>...", args[0]);
+        std::process::exit(2);
+    }
+    let addr: SocketAddr = args[1].parse().expect("invalid listen address");
+
+    let pool_size = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut registry = ModuleRegistry::with_cache(CACHE_CAPACITY);
+    for path in &args[2..] {
+        let wasm = std::fs::read(path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        let circuit_id = registry
+            .register(&wasm, pool_size, HostConfig::default())
+            .unwrap_or_else(|e| panic!("{}: {}", path, e));
+        eprintln!("{}: circuit {:?}", path, circuit_id);
+    }
+
+    let app = Router::new()
+        .route("/verify", routing::post(verify))
+        .route("/circuits", routing::get(circuits))
+        .with_state(Arc::new(registry));
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    eprintln!("listening on {}", addr);
+    axum::serve(listener, app).await.unwrap();
+}
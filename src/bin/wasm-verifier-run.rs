@@ -0,0 +1,108 @@
+//! Verify a proof out-of-band through a verifier wasm module and report the result as JSON.
+//!
+//! usage: wasm-verifier-run <module.wasm> <proof.bin> <instances.bin>
+//!
+//! The instances file holds the public inputs encoded with `encode_instances`, as in the
+//! guest ABI. Exits with 0 when the proof is valid, 1 when it is not and 2 on errors.
+
+use std::{process::ExitCode, time::Instant};
+
+use wasm_verifier_arithmetic::{
+    abi::Verdict,
+    host::{CompiledModule, HostConfig, HostError},
+};
+
+struct Report {
+    verdict: Verdict,
+    compile_ms: u128,
+    instantiate_ms: u128,
+    verify_ms: u128,
+}
+
+fn run(wasm: &[u8], proof: &[u8], instances: &[u8]) -> Result<Report, HostError> {
+    let now = Instant::now();
+    let compiled = CompiledModule::new(wasm, HostConfig::default())?;
+    let compile_ms = now.elapsed().as_millis();
+
+    let now = Instant::now();
+    let mut harness = compiled.instantiate()?;
+    let instantiate_ms = now.elapsed().as_millis();
+
+    let now = Instant::now();
+    let verdict = harness.verify(proof, instances)?;
+    let verify_ms = now.elapsed().as_millis();
+
+    Ok(Report {
+        verdict,
+        compile_ms,
+        instantiate_ms,
+        verify_ms,
+    })
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        eprintln!(
+            "usage: {} <module.wasm> <proof.bin> <instances.bin>",
+            args[0]
+        );
+        return ExitCode::from(2);
+    }
+
+    let inputs = read(&args[1]).and_then(|wasm| {
+        let proof = read(&args[2])?;
+        let instances = read(&args[3])?;
+        Ok((wasm, proof, instances))
+    });
+    let result = inputs.and_then(|(wasm, proof, instances)| {
+        run(&wasm, &proof, &instances).map_err(|e| e.to_string())
+    });
+
+    match result {
+        Ok(report) => {
+            let (status, message) = match &report.verdict {
+                Verdict::Valid => ("valid", ""),
+                Verdict::Invalid(msg) => ("invalid", msg.as_str()),
+                Verdict::Malformed(msg) => ("malformed", msg.as_str()),
+                Verdict::Error(msg) => ("error", msg.as_str()),
+            };
+            println!(
+                "{{\"status\":\"{}\",\"message\":{},\"compile_ms\":{},\"instantiate_ms\":{},\"verify_ms\":{}}}",
+                status,
+                json_string(message),
+                report.compile_ms,
+                report.instantiate_ms,
+                report.verify_ms
+            );
+            if report.verdict.is_valid() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            }
+        }
+        Err(e) => {
+            println!("{{\"status\":\"error\",\"message\":{}}}", json_string(&e));
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Sizing guest memory for verification, and arena allocation behind the `bounded_alloc`
+//! and `arena` features.
+//!
+//! halo2 allocates freely while verifying, so instead of changing it the guest installs a
+//! global allocator that can switch to an arena reserved up front. Inside `with_arena`
+//! every allocation is bumped out of that arena and frees are no-ops, so verification
+//! never touches the heap or grows linear memory. Running out of arena fails the
+//! allocation, which aborts the guest instead of growing.
+//!
+//! Outside `with_arena` allocations go to the system allocator as usual. Values made in
+//! the arena stay readable until the next `with_arena`, which is how the verdict gets out.
+//!
+//! The `arena` feature uses the same arena for speed rather than for a bound: malloc is
+//! slow under singlepass, and most of what `verify_proof` allocates is short-lived
+//! vectors. `with_arena_or_heap` bumps allocations out of the arena while it lasts and
+//! falls back to the system allocator once it runs out, instead of failing.
+//!
+//! Without either feature, `preallocate` grows the ordinary heap to the same estimate
+//! once, so verification is served from memory the allocator already holds.
+
+use crate::VerifyingKey;
+
+/// Upper estimate of what one verification allocates for `vk`. The final MSM holds one
+/// scalar per generator, and halo2 keeps a few vectors of that size around while building
+/// it. The rest scales with the proof.
+pub fn scratch_bytes(vk: &VerifyingKey) -> usize {
+    let n = 1usize << vk.k;
+    4 * 32 * n + 8 * vk.proof_size() + 64 * 1024
+}
+
+/// Grow the heap by `bytes` up front, so verification doesn't hit `memory.grow`, which
+/// is slow under singlepass. The memory is allocated and freed at once; the allocator
+/// keeps it, wasm memory never shrinks.
+#[cfg(target_arch = "wasm32")]
+pub fn preallocate(bytes: usize) {
+    // Chunks above dlmalloc's mmap threshold go back to the system when freed, which on
+    // wasm leaks them, so stay well below it
+    const CHUNK: usize = 64 * 1024;
+    let chunks: Vec<Vec<u8>> = (0..bytes.div_ceil(CHUNK))
+        .map(|_| Vec::with_capacity(CHUNK))
+        .collect();
+    drop(chunks);
+}
+
+#[cfg(all(
+    target_arch = "wasm32",
+    any(feature = "bounded_alloc", feature = "arena")
+))]
+pub use arena::{reserve, with_arena, with_arena_or_heap};
+
+#[cfg(any(
+    test,
+    all(
+        target_arch = "wasm32",
+        any(feature = "bounded_alloc", feature = "arena")
+    )
+))]
+mod accounting {
+    //! The arena's bookkeeping over plain addresses: the region it was given, the bump
+    //! pointer, whether allocations go to it and what happens when it runs out. Only the
+    //! wasm global allocator below hands it real memory, so it is tested natively.
+
+    use core::{alloc::Layout, cell::Cell};
+
+    /// Where an allocation goes, as decided by `Arena::alloc`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub(super) enum Placement {
+        /// Outside the arena, or past its end in `with_arena_or_heap`, to the system
+        /// allocator
+        Heap,
+        Arena(usize),
+        /// Inside `with_arena` but the arena is out of space
+        Full,
+    }
+
+    pub(super) struct Arena {
+        start: Cell<usize>,
+        end: Cell<usize>,
+        next: Cell<usize>,
+        active: Cell<bool>,
+        /// Allocations that don't fit go to the heap rather than failing
+        fallback: Cell<bool>,
+    }
+
+    impl Arena {
+        pub(super) const fn new() -> Self {
+            Arena {
+                start: Cell::new(0),
+                end: Cell::new(0),
+                next: Cell::new(0),
+                active: Cell::new(false),
+                fallback: Cell::new(false),
+            }
+        }
+
+        pub(super) fn is_reserved(&self) -> bool {
+            self.start.get() != 0
+        }
+
+        pub(super) fn capacity(&self) -> usize {
+            self.end.get() - self.start.get()
+        }
+
+        pub(super) fn set_region(&self, start: usize, len: usize) {
+            self.start.set(start);
+            self.end.set(start + len);
+            self.next.set(start);
+        }
+
+        /// Empty the arena and send allocations to it, and to the heap once it is full if
+        /// `fallback`
+        pub(super) fn enter(&self, fallback: bool) {
+            self.next.set(self.start.get());
+            self.fallback.set(fallback);
+            self.active.set(true);
+        }
+
+        pub(super) fn exit(&self) {
+            self.active.set(false);
+        }
+
+        pub(super) fn owns(&self, addr: usize) -> bool {
+            addr >= self.start.get() && addr < self.end.get()
+        }
+
+        pub(super) fn alloc(&self, layout: Layout) -> Placement {
+            if !self.active.get() {
+                return Placement::Heap;
+            }
+            let start = self
+                .next
+                .get()
+                .checked_add(layout.align() - 1)
+                .map(|next| next & !(layout.align() - 1));
+            match start.and_then(|start| Some((start, start.checked_add(layout.size())?))) {
+                Some((start, end)) if end <= self.end.get() => {
+                    self.next.set(end);
+                    Placement::Arena(start)
+                }
+                _ if self.fallback.get() => Placement::Heap,
+                _ => Placement::Full,
+            }
+        }
+
+        /// Grow the allocation at `addr` to `new_size` where it is, which works for the
+        /// latest one while there's room, as when pushing to a fresh Vec
+        pub(super) fn grow_in_place(&self, addr: usize, old_size: usize, new_size: usize) -> bool {
+            let fits = self.active.get()
+                && addr + old_size == self.next.get()
+                && addr
+                    .checked_add(new_size)
+                    .is_some_and(|end| end <= self.end.get());
+            if fits {
+                self.next.set(addr + new_size);
+            }
+            fits
+        }
+    }
+
+    // The guest is single-threaded
+    #[cfg(target_arch = "wasm32")]
+    unsafe impl Sync for Arena {}
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_arena_bookkeeping() {
+            let arena = Arena::new();
+            assert!(!arena.is_reserved());
+            arena.set_region(0x1000, 64);
+            assert_eq!(arena.capacity(), 64);
+
+            let layout = |size, align| Layout::from_size_align(size, align).unwrap();
+            assert_eq!(arena.alloc(layout(8, 8)), Placement::Heap);
+
+            arena.enter(false);
+            assert_eq!(arena.alloc(layout(3, 1)), Placement::Arena(0x1000));
+            // Aligned up past the 3 bytes
+            assert_eq!(arena.alloc(layout(8, 8)), Placement::Arena(0x1008));
+            assert!(arena.owns(0x1008) && !arena.owns(0x1040) && !arena.owns(0xfff));
+
+            // Only the latest allocation grows in place, and only while it fits
+            assert!(!arena.grow_in_place(0x1000, 3, 6));
+            assert!(arena.grow_in_place(0x1008, 8, 24));
+            assert!(!arena.grow_in_place(0x1008, 24, 64));
+            assert_eq!(arena.alloc(layout(32, 16)), Placement::Arena(0x1020));
+            assert_eq!(arena.alloc(layout(1, 1)), Placement::Full);
+            assert_eq!(arena.alloc(layout(isize::MAX as usize, 1)), Placement::Full);
+            arena.exit();
+
+            // Frees are no-ops, entering again starts from the bottom
+            assert!(!arena.grow_in_place(0x1020, 32, 32));
+            arena.enter(false);
+            assert_eq!(arena.alloc(layout(64, 1)), Placement::Arena(0x1000));
+        }
+
+        #[test]
+        fn test_arena_fallback() {
+            let arena = Arena::new();
+            arena.set_region(0x1000, 64);
+            let layout = |size| Layout::from_size_align(size, 8).unwrap();
+
+            arena.enter(true);
+            assert_eq!(arena.alloc(layout(48)), Placement::Arena(0x1000));
+            // What doesn't fit goes to the heap, what still does keeps bumping
+            assert_eq!(arena.alloc(layout(32)), Placement::Heap);
+            assert_eq!(arena.alloc(layout(16)), Placement::Arena(0x1030));
+            assert!(!arena.owns(0x1040));
+            arena.exit();
+
+            // Failing stays the default for the next `with_arena`
+            arena.enter(false);
+            assert_eq!(arena.alloc(layout(48)), Placement::Arena(0x1000));
+            assert_eq!(arena.alloc(layout(32)), Placement::Full);
+        }
+    }
+}
+
+#[cfg(all(
+    target_arch = "wasm32",
+    any(feature = "bounded_alloc", feature = "arena")
+))]
+mod arena {
+    use std::alloc::{GlobalAlloc, Layout, System};
+
+    use super::accounting::{Arena, Placement};
+
+    static ARENA: Arena = Arena::new();
+
+    struct BoundedAlloc;
+
+    unsafe impl GlobalAlloc for BoundedAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            match ARENA.alloc(layout) {
+                Placement::Arena(addr) => addr as *mut u8,
+                Placement::Heap => System.alloc(layout),
+                Placement::Full => core::ptr::null_mut(),
+            }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            if !ARENA.owns(ptr as usize) {
+                System.dealloc(ptr, layout);
+            }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            if !ARENA.owns(ptr as usize) {
+                return System.realloc(ptr, layout, new_size);
+            }
+            if ARENA.grow_in_place(ptr as usize, layout.size(), new_size) {
+                return ptr;
+            }
+            let new = self.alloc(Layout::from_size_align_unchecked(new_size, layout.align()));
+            if !new.is_null() {
+                core::ptr::copy_nonoverlapping(ptr, new, layout.size().min(new_size));
+            }
+            new
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: BoundedAlloc = BoundedAlloc;
+
+    /// Reserve the arena. Only the first call allocates, later ones report whether the
+    /// existing arena is at least `bytes` large.
+    pub fn reserve(bytes: usize) -> bool {
+        if ARENA.is_reserved() {
+            return ARENA.capacity() >= bytes;
+        }
+        let Ok(layout) = Layout::from_size_align(bytes, 16) else {
+            return false;
+        };
+        let start = unsafe { System.alloc(layout) } as usize;
+        if start == 0 {
+            return false;
+        }
+        ARENA.set_region(start, bytes);
+        true
+    }
+
+    /// Run `f` allocating only from the arena, which is emptied first
+    pub fn with_arena<T>(f: impl FnOnce() -> T) -> T {
+        run(false, f)
+    }
+
+    /// Like `with_arena`, but allocations that don't fit go to the system allocator
+    pub fn with_arena_or_heap<T>(f: impl FnOnce() -> T) -> T {
+        run(true, f)
+    }
+
+    fn run<T>(fallback: bool, f: impl FnOnce() -> T) -> T {
+        ARENA.enter(fallback);
+        let result = f();
+        ARENA.exit();
+        result
+    }
+}
@@ -0,0 +1,193 @@
+//! Bulk verification of (proof, instances) rows, e.g. to audit historical chain data,
+//! behind the `bulk` feature. `wasm-verifier-bulk` runs it over a file.
+//!
+//! Rows come from CSV with a `proof,instances` header and hex columns, or with the
+//! `parquet` feature from Parquet with binary `proof` and `instances` columns. Instances
+//! are in the `encode_instances` encoding. Rows are verified in chunks through the batch
+//! verifier, one final MSM per chunk, falling back to single verification only for
+//! chunks that fail. Results are written as CSV, one `row,status,message` line per row.
+
+use std::io::{self, BufRead, Write};
+
+use crate::{abi::Verdict, batch::BatchVerifier, decode_instances, hex, Proof, VerifyingKey};
+
+const K: u32 = 4;
+
+/// Rows folded into one batch
+pub const DEFAULT_CHUNK: usize = 256;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+    pub proof: Vec<u8>,
+    pub instances: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum BulkError {
+    Io(io::Error),
+    /// A line or record that isn't a row, with its 1-based line or record number
+    Malformed(usize, String),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+}
+
+impl core::fmt::Display for BulkError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BulkError::Io(e) => write!(f, "{}", e),
+            BulkError::Malformed(line, msg) => write!(f, "line {}: {}", line, msg),
+            #[cfg(feature = "parquet")]
+            BulkError::Parquet(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BulkError {}
+
+impl From<io::Error> for BulkError {
+    fn from(e: io::Error) -> Self {
+        BulkError::Io(e)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for BulkError {
+    fn from(e: parquet::errors::ParquetError) -> Self {
+        BulkError::Parquet(e)
+    }
+}
+
+/// Rows from CSV with a `proof,instances` header. Blank lines are skipped.
+pub fn read_csv(reader: impl BufRead) -> Result<Vec<Row>, BulkError> {
+    let mut lines = reader.lines().enumerate();
+    let header = lines.next().map(|(_, line)| line).transpose()?;
+    if header.as_deref().map(str::trim) != Some("proof,instances") {
+        return Err(BulkError::Malformed(
+            1,
+            "expected a proof,instances header".into(),
+        ));
+    }
+
+    let mut rows = vec![];
+    for (i, line) in lines {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let malformed = |msg: String| BulkError::Malformed(i + 1, msg);
+        let (proof, instances) = line
+            .split_once(',')
+            .ok_or_else(|| malformed("expected two columns".into()))?;
+        rows.push(Row {
+            proof: hex::decode(proof).map_err(|e| malformed(format!("proof: {}", e)))?,
+            instances: hex::decode(instances)
+                .map_err(|e| malformed(format!("instances: {}", e)))?,
+        });
+    }
+    Ok(rows)
+}
+
+/// Rows from a Parquet file with binary `proof` and `instances` columns
+#[cfg(feature = "parquet")]
+pub fn read_parquet(file: std::fs::File) -> Result<Vec<Row>, BulkError> {
+    use parquet::{
+        file::reader::{FileReader, SerializedFileReader},
+        record::Field,
+    };
+
+    let reader = SerializedFileReader::new(file)?;
+    let mut rows = vec![];
+    for (i, record) in reader.get_row_iter(None)?.enumerate() {
+        let record = record?;
+        let (mut proof, mut instances) = (None, None);
+        for (name, field) in record.get_column_iter() {
+            if let Field::Bytes(bytes) = field {
+                match name.as_str() {
+                    "proof" => proof = Some(bytes.data().to_vec()),
+                    "instances" => instances = Some(bytes.data().to_vec()),
+                    _ => {}
+                }
+            }
+        }
+        match (proof, instances) {
+            (Some(proof), Some(instances)) => rows.push(Row { proof, instances }),
+            _ => {
+                return Err(BulkError::Malformed(
+                    i + 1,
+                    "expected binary proof and instances columns".into(),
+                ))
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// One verdict per row, in order. Rows that don't decode or don't fit the circuit are
+/// `Malformed` and stay out of the batches.
+pub fn verify_rows(vk: &VerifyingKey, rows: &[Row], chunk: usize) -> Vec<Verdict> {
+    let mut verdicts = Vec::with_capacity(rows.len());
+    for rows in rows.chunks(chunk.max(1)) {
+        let decoded: Vec<_> = rows
+            .iter()
+            .map(|row| {
+                Proof::validate_len(row.proof.len(), vk)?;
+                let instances = decode_instances(&row.instances)?;
+                vk.check_instances(&[&instances])?;
+                Ok((Proof::new(row.proof.clone()), instances))
+            })
+            .collect::<Vec<crate::Result<_>>>();
+
+        let mut batch = BatchVerifier::new(vk);
+        let mut batched = vec![];
+        for (i, row) in decoded.iter().enumerate() {
+            if let Ok((proof, instances)) = row {
+                batch.add(proof, &[instances]);
+                batched.push(i);
+            }
+        }
+        let invalid = batch.finalize_or_find_invalid().err().unwrap_or_default();
+
+        let start = verdicts.len();
+        verdicts.extend(decoded.into_iter().map(|row| match row {
+            Ok(_) => Verdict::Valid,
+            Err(e) => Verdict::Malformed(e.to_string()),
+        }));
+        for i in invalid {
+            verdicts[start + batched[i]] = Verdict::Invalid("proof did not verify".into());
+        }
+    }
+    verdicts
+}
+
+fn csv_field(s: &str) -> String {
+    match s.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", s.replace('"', "\"\"")),
+        false => s.to_string(),
+    }
+}
+
+/// `row,status,message` lines, rows numbered from 1 and the status named after its verdict
+pub fn write_csv(mut writer: impl Write, verdicts: &[Verdict]) -> io::Result<()> {
+    writeln!(writer, "row,status,message")?;
+    for (i, verdict) in verdicts.iter().enumerate() {
+        let (status, message) = match verdict {
+            Verdict::Valid => ("valid", ""),
+            Verdict::Invalid(msg) => ("invalid", msg.as_str()),
+            Verdict::Malformed(msg) => ("malformed", msg.as_str()),
+            Verdict::Error(msg) => ("error", msg.as_str()),
+        };
+        writeln!(writer, "{},{},{}", i + 1, status, csv_field(message))?;
+    }
+    Ok(())
+}
+
+/// The vk for the bulk job: the arithmetic circuit's, from `VerifyingKey::to_bytes`
+/// output if given, from keygen otherwise
+pub fn arith_vk(bytes: Option<&[u8]>) -> crate::Result<VerifyingKey> {
+    let vk = match bytes {
+        Some(bytes) => VerifyingKey::from_bytes(bytes, crate::MyCircuit::default())?,
+        None => VerifyingKey::build(K, &crate::MyCircuit::default())?,
+    };
+    Ok(vk.with_instance_lengths(crate::MyCircuit::INSTANCE_LENGTHS.to_vec()))
+}
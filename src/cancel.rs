@@ -0,0 +1,128 @@
+//! Aborting verification early, e.g. when the block being validated is reorged away or a
+//! request times out. The token is checked before a proof starts and at the boundaries
+//! between halo2's verification phases (see report.rs), not inside them.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use halo2_proofs::{
+    pasta::{pallas, vesta},
+    plonk::{self, VerificationStrategy},
+    poly::commitment::{Guard, MSM},
+};
+
+use crate::{ensure_consumed, Error, Proof, Result, VerifyingKey};
+
+/// Cheap to clone, every clone cancels the same work
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn check(&self) -> Result<()> {
+        match self.is_cancelled() {
+            true => Err(Error::Cancelled),
+            false => Ok(()),
+        }
+    }
+}
+
+/// `SingleVerifier` with a cancellation check before each phase. halo2 wants a
+/// `plonk::Error` back from the strategy, so a cancellation is noted in `cancelled`.
+struct CancellableVerifier<'params, 't> {
+    msm: MSM<'params, vesta::Affine>,
+    token: &'t CancelToken,
+    cancelled: &'t mut bool,
+}
+
+impl<'params> VerificationStrategy<'params, vesta::Affine> for CancellableVerifier<'params, '_> {
+    type Output = ();
+
+    fn process(
+        self,
+        f: impl FnOnce(
+            MSM<'params, vesta::Affine>,
+        ) -> std::result::Result<Guard<'params, vesta::Affine>, plonk::Error>,
+    ) -> std::result::Result<Self::Output, plonk::Error> {
+        if self.token.is_cancelled() {
+            *self.cancelled = true;
+            return Err(plonk::Error::Synthesis);
+        }
+        let guard = f(self.msm)?;
+
+        if self.token.is_cancelled() {
+            *self.cancelled = true;
+            return Err(plonk::Error::Synthesis);
+        }
+        match guard.use_challenges().eval() {
+            true => Ok(()),
+            false => Err(plonk::Error::ConstraintSystemFailure),
+        }
+    }
+}
+
+impl Proof {
+    /// Like `verify`, but gives up with `Error::Cancelled` once `token` is cancelled
+    pub fn verify_cancellable(
+        &self,
+        vk: &VerifyingKey,
+        instances: &[&[pallas::Base]],
+        token: &CancelToken,
+    ) -> Result<()> {
+        token.check()?;
+
+        let mut cancelled = false;
+        let strategy = CancellableVerifier {
+            msm: vk.params.empty_msm(),
+            token,
+            cancelled: &mut cancelled,
+        };
+        let mut reader = self.as_ref();
+        let mut transcript = crate::transcript::reader(&mut reader);
+        let result =
+            plonk::verify_proof(&vk.params, &vk.vk, strategy, &[instances], &mut transcript);
+        if cancelled {
+            return Err(Error::Cancelled);
+        }
+        result?;
+        ensure_consumed(reader)?;
+        Ok(())
+    }
+}
+
+/// `verify_many` that stops early once `token` is cancelled. Proofs not verified by then
+/// get `Error::Cancelled`.
+pub fn verify_many_cancellable(
+    vk: &VerifyingKey,
+    items: &[(&Proof, &[&[pallas::Base]])],
+    token: &CancelToken,
+) -> Vec<Result<()>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use rayon::prelude::*;
+        items
+            .par_iter()
+            .map(|(proof, instances)| proof.verify_cancellable(vk, instances, token))
+            .collect()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        items
+            .iter()
+            .map(|(proof, instances)| proof.verify_cancellable(vk, instances, token))
+            .collect()
+    }
+}
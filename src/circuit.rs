@@ -0,0 +1,92 @@
+//! The arithmetic circuit from the crate docs.
+//!
+//! Also compiled into build.rs by the `embedded_keys` feature, so it may only refer to
+//! `crate::gadget` and `halo2_proofs`.
+
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    pasta::pallas,
+    plonk::{self, Advice, Circuit, Column, ConstraintSystem, Instance},
+};
+
+use crate::gadget::{
+    arithmetic::{ArithChip, ArithConfig, ArithInstruction},
+    assign_free_advice,
+};
+
+#[derive(Clone)]
+pub(crate) struct MyConfig {
+    instance: Column<Instance>,
+    advices: [Column<Advice>; 3],
+    arith_config: ArithConfig,
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct MyCircuit {
+    pub(crate) a: Value<pallas::Base>,
+    pub(crate) b: Value<pallas::Base>,
+}
+
+impl MyCircuit {
+    /// Sum, product and difference, all in the one instance column
+    pub(crate) const INSTANCE_LENGTHS: [usize; 1] = [3];
+}
+
+/// The public inputs the witness `(a, b)` proves: sum, product and difference
+pub fn expected_instances(a: pallas::Base, b: pallas::Base) -> Vec<pallas::Base> {
+    vec![a + b, a * b, a - b]
+}
+
+// By using a trait bound with an impl block that uses generic type parameters,
+// we can implement methods conditionally for types that implement the specified traits.
+// https://doc.rust-lang.org/book/ch10-02-traits.html
+impl Circuit<pallas::Base> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+        let advices = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let instance = meta.instance_column();
+
+        meta.enable_equality(instance);
+        meta.enable_equality(advices[0]);
+        meta.enable_equality(advices[1]);
+        meta.enable_equality(advices[2]);
+
+        Self::Config {
+            instance,
+            advices,
+            arith_config: ArithChip::configure(meta, advices[0], advices[1], advices[2]),
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<pallas::Base>,
+    ) -> std::result::Result<(), plonk::Error> {
+        let arith_chip = ArithChip::construct(config.arith_config);
+        let a = assign_free_advice(layouter.namespace(|| "load a"), config.advices[0], self.a)?;
+        let b = assign_free_advice(layouter.namespace(|| "load b"), config.advices[1], self.b)?;
+
+        let sum = arith_chip.add(layouter.namespace(|| "a + b"), &a, &b)?;
+        layouter.constrain_instance(sum.cell(), config.instance, 0)?;
+
+        // Q: Why is the selector cell of the mul region in a different column that those of sum and sub gate?
+        let product = arith_chip.mul(layouter.namespace(|| "a * b"), &a, &b)?;
+        layouter.constrain_instance(product.cell(), config.instance, 1)?;
+
+        let diff = arith_chip.sub(layouter.namespace(|| "a - b"), &a, &b)?;
+        layouter.constrain_instance(diff.cell(), config.instance, 2)?;
+
+        Ok(())
+    }
+}
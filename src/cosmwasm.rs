@@ -0,0 +1,117 @@
+//! CosmWasm contract entrypoints around the verify API, behind the `cosmwasm` feature.
+//! `make cosmwasm` builds the contract, without the fixture entrypoints.
+//!
+//! Messages are JSON with base64 byte strings, instances in the `encode_instances`
+//! encoding:
+//! - instantiate `{"vk": ...}` stores a vk serialized by `VerifyingKey::to_bytes`. Without
+//!   one the contract builds the arithmetic circuit's key on every call.
+//! - execute `{"verify": {"proof": ..., "instances": ...}}` fails the transaction unless
+//!   the proof is valid.
+//! - query `{"verify": {...}}` answers `{"status": 0, "message": ""}` with the
+//!   `abi::STATUS_` code; query `{"circuit_id": {}}` answers the circuit id.
+
+use cosmwasm_std::{
+    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult, Storage,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::{verify_buffers, Verdict, CIRCUIT_ID},
+    verify_bytes, MyCircuit, VerifyingKey,
+};
+
+const VK_KEY: &[u8] = b"vk";
+
+#[derive(Deserialize)]
+pub struct InstantiateMsg {
+    pub vk: Option<Binary>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    Verify { proof: Binary, instances: Binary },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Verify { proof: Binary, instances: Binary },
+    CircuitId {},
+}
+
+#[derive(Serialize)]
+pub struct VerifyResponse {
+    pub status: u8,
+    pub message: String,
+}
+
+fn stored_vk(storage: &dyn Storage) -> Option<Result<VerifyingKey, String>> {
+    let bytes = storage.get(VK_KEY)?;
+    Some(
+        VerifyingKey::from_bytes(&bytes, MyCircuit::default())
+            .map(|vk| vk.with_instance_lengths(MyCircuit::INSTANCE_LENGTHS.to_vec()))
+            .map_err(|e| e.to_string()),
+    )
+}
+
+fn verdict(storage: &dyn Storage, proof: &[u8], instances: &[u8]) -> Verdict {
+    match stored_vk(storage) {
+        Some(Ok(vk)) => Verdict::of(verify_bytes(&vk, proof, instances)),
+        Some(Err(msg)) => Verdict::Error(msg),
+        None => verify_buffers(proof, instances),
+    }
+}
+
+#[entry_point]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    if let Some(vk) = msg.vk {
+        VerifyingKey::from_bytes(&vk, MyCircuit::default())
+            .map_err(|e| StdError::generic_err(format!("invalid verifying key: {}", e)))?;
+        deps.storage.set(VK_KEY, &vk);
+    }
+    Ok(Response::new().add_attribute("circuit_id", CIRCUIT_ID))
+}
+
+#[entry_point]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: ExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::Verify { proof, instances } => {
+            match verdict(deps.storage, &proof, &instances) {
+                Verdict::Valid => Ok(Response::new().add_attribute("verdict", "valid")),
+                verdict => Err(StdError::generic_err(format!("{:?}", verdict))),
+            }
+        }
+    }
+}
+
+#[entry_point]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Verify { proof, instances } => {
+            let verdict = verdict(deps.storage, &proof, &instances);
+            let message = match &verdict {
+                Verdict::Valid => String::new(),
+                Verdict::Invalid(msg) | Verdict::Malformed(msg) | Verdict::Error(msg) => {
+                    msg.clone()
+                }
+            };
+            to_json_binary(&VerifyResponse {
+                status: verdict.status(),
+                message,
+            })
+        }
+        QueryMsg::CircuitId {} => to_json_binary(CIRCUIT_ID),
+    }
+}
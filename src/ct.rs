@@ -0,0 +1,39 @@
+//! Constant-time equality for values that consensus code compares, so the time an
+//! equality check takes doesn't reveal how long a common prefix is.
+
+use halo2_proofs::pasta::pallas;
+use subtle::{Choice, ConstantTimeEq};
+
+/// Constant-time in the contents, lengths are compared up front since they aren't secret
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && bool::from(a.ct_eq(b))
+}
+
+pub fn ct_eq_instances(a: &[pallas::Base], b: &[pallas::Base]) -> bool {
+    a.len() == b.len()
+        && bool::from(
+            a.iter()
+                .zip(b)
+                .fold(Choice::from(1), |eq, (a, b)| eq & a.ct_eq(b)),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ct_eq() {
+        assert!(ct_eq(b"proof", b"proof"));
+        assert!(!ct_eq(b"proof", b"proog"));
+        assert!(!ct_eq(b"proof", b"proo"));
+
+        let a = [pallas::Base::from(1), pallas::Base::from(2)];
+        assert!(ct_eq_instances(&a, &a));
+        assert!(!ct_eq_instances(&a, &a[..1]));
+        assert!(!ct_eq_instances(
+            &a,
+            &[pallas::Base::from(1), pallas::Base::from(3)]
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+//! darkfi contract section entrypoints around the verify logic, behind the `darkfi`
+//! feature, so the module deploys into a darkfi node as a contract. `make wasm-darkfi`
+//! builds it; `host::Harness::call_section` runs the sections under this crate's host.
+//!
+//! Every section gets a pointer to its input: the contract id (32 bytes), the payload
+//! length as u64 LE, then the payload. For `__metadata`, `__entrypoint` and `__update` the
+//! payload is darkfi's call data: the index of the call being run as u32 LE and the
+//! transaction's calls, each a contract id and a VarInt-prefixed data buffer. This
+//! contract's data is a function byte (`FUNC_VERIFY`), the proof prefixed with its length
+//! as a VarInt, then the instances in the `encode_instances` encoding.
+//!
+//! Sections return 0 on success and otherwise the `abi::STATUS_` code. `__metadata`
+//! returns the proof's public inputs, under the zkas namespace `Arith`, and no signature
+//! keys through `set_return_data`.
+
+use halo2_proofs::pasta::group::ff::PrimeField;
+
+use crate::{
+    abi::{self, verify_buffers, Verdict, STATUS_ERROR, STATUS_MALFORMED},
+    decode_instances,
+};
+
+pub const FUNC_VERIFY: u8 = 0x00;
+
+const SUCCESS: u64 = 0;
+const ZKAS_NAMESPACE: &str = "Arith";
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn drk_log(ptr: *const u8, len: u32);
+    fn set_return_data(ptr: *const u8, len: u32) -> i64;
+}
+
+fn log(msg: &str) {
+    unsafe { drk_log(msg.as_ptr(), msg.len() as u32) }
+}
+
+/// darkfi_serial's VarInt: one byte below 0xfd, else a marker byte and a u16, u32 or u64
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let (&first, rest) = bytes.split_first()?;
+    let width = match first {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        n => {
+            *bytes = rest;
+            return Some(n as u64);
+        }
+    };
+    let (n, rest) = (rest.get(..width)?, &rest[width..]);
+    *bytes = rest;
+    let mut buf = [0u8; 8];
+    buf[..width].copy_from_slice(n);
+    Some(u64::from_le_bytes(buf))
+}
+
+fn write_varint(out: &mut Vec<u8>, n: u64) {
+    match n {
+        0..=0xfc => out.push(n as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend_from_slice(&(n as u16).to_le_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            out.push(0xfe);
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        _ => {
+            out.push(0xff);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    let taken = bytes.get(..len)?;
+    *bytes = &bytes[len..];
+    Some(taken)
+}
+
+/// The payload of a section's input
+unsafe fn payload<'a>(input: *const u8) -> &'a [u8] {
+    let len = u64::from_le_bytes(*(input.add(32) as *const [u8; 8]));
+    core::slice::from_raw_parts(input.add(40), len as usize)
+}
+
+/// This contract's data in the call data, i.e. that of the call being run
+fn own_call(mut call_data: &[u8]) -> Option<&[u8]> {
+    let idx = u32::from_le_bytes(take(&mut call_data, 4)?.try_into().unwrap());
+    let calls = read_varint(&mut call_data)?;
+    for i in 0..calls {
+        take(&mut call_data, 32)?;
+        let len = read_varint(&mut call_data)? as usize;
+        let data = take(&mut call_data, len)?;
+        if i == idx as u64 {
+            return Some(data);
+        }
+    }
+    None
+}
+
+/// Proof and instances out of this contract's call data
+fn verify_call(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (&func, mut rest) = data.split_first()?;
+    if func != FUNC_VERIFY {
+        return None;
+    }
+    let len = read_varint(&mut rest)? as usize;
+    let proof = take(&mut rest, len)?;
+    Some((proof, rest))
+}
+
+fn parse(input: *const u8) -> Option<(&'static [u8], &'static [u8])> {
+    verify_call(own_call(unsafe { payload(input) })?)
+}
+
+/// Builds the vk, so later calls don't pay for keygen
+#[no_mangle]
+pub extern "C" fn __initialize(_input: *const u8) -> u64 {
+    abi::build_vk();
+    SUCCESS
+}
+
+#[no_mangle]
+pub extern "C" fn __metadata(input: *const u8) -> u64 {
+    let Some((_, instances)) = parse(input) else {
+        return STATUS_MALFORMED as u64;
+    };
+    let Ok(instances) = decode_instances(instances) else {
+        return STATUS_MALFORMED as u64;
+    };
+
+    // (Vec<(String, Vec<pallas::Base>)>, Vec<PublicKey>)
+    let mut metadata = vec![];
+    write_varint(&mut metadata, 1);
+    write_varint(&mut metadata, ZKAS_NAMESPACE.len() as u64);
+    metadata.extend_from_slice(ZKAS_NAMESPACE.as_bytes());
+    write_varint(&mut metadata, instances.len() as u64);
+    for instance in &instances {
+        metadata.extend_from_slice(&instance.to_repr());
+    }
+    write_varint(&mut metadata, 0);
+
+    match unsafe { set_return_data(metadata.as_ptr(), metadata.len() as u32) } {
+        0 => SUCCESS,
+        _ => STATUS_ERROR as u64,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn __entrypoint(input: *const u8) -> u64 {
+    let Some((proof, instances)) = parse(input) else {
+        return STATUS_MALFORMED as u64;
+    };
+    match verify_buffers(proof, instances) {
+        Verdict::Valid => SUCCESS,
+        verdict => {
+            log(&format!("{:?}", verdict));
+            verdict.status() as u64
+        }
+    }
+}
+
+/// Verification changes no state, there is nothing to apply
+#[no_mangle]
+pub extern "C" fn __update(_input: *const u8) -> u64 {
+    SUCCESS
+}
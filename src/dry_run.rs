@@ -0,0 +1,35 @@
+//! Checking a circuit's constraints against a witness without proving, through halo2's
+//! `MockProver`, so circuit authors don't need halo2's dev APIs.
+
+use halo2_proofs::{dev::MockProver, pasta::pallas, plonk::Circuit};
+
+/// Why a dry run failed, one halo2 description per failed constraint. A circuit that can't
+/// even be synthesized at size k gets a single entry saying so.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintFailures(pub Vec<String>);
+
+impl core::fmt::Display for ConstraintFailures {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} constraint failure(s)", self.0.len())?;
+        for failure in &self.0 {
+            write!(f, "\n  {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConstraintFailures {}
+
+/// `instances` holds one slice per instance column
+pub fn dry_run<C: Circuit<pallas::Base>>(
+    k: u32,
+    circuit: &C,
+    instances: &[&[pallas::Base]],
+) -> Result<(), ConstraintFailures> {
+    let instances = instances.iter().map(|column| column.to_vec()).collect();
+    let prover = MockProver::run(k, circuit, instances)
+        .map_err(|e| ConstraintFailures(vec![format!("synthesis failed: {}", e)]))?;
+    prover
+        .verify()
+        .map_err(|failures| ConstraintFailures(failures.iter().map(|f| f.to_string()).collect()))
+}
@@ -0,0 +1,279 @@
+//! Self-describing proof encoding. The bare transcript in `Proof` says nothing about what
+//! it proves, so the envelope prefixes it with a header that `verify` checks first.
+//!
+//! Layout: magic `WVPF`, format version, circuit id (32 bytes), k as u32 LE, instance
+//! count as u32 LE, then the transcript bytes. The circuit id is `VerifyingKey::digest`.
+//!
+//! The compact variant (magic `WVPC`) stores k, the instance count and the transcript
+//! length as LEB128 varints instead, which only shrinks the header. The transcript bytes
+//! pass through unchanged in both: there is no compressed point encoding to add, halo2
+//! already writes every curve point as a 32-byte compressed point and every scalar in 32
+//! bytes. Varints must be minimal, so one proof has exactly one compact encoding.
+
+use halo2_proofs::{pasta::pallas, plonk};
+
+use crate::{Proof, VerifyingKey};
+
+const ENVELOPE_MAGIC: &[u8; 4] = b"WVPF";
+pub const ENVELOPE_VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 32 + 4 + 4;
+const COMPACT_MAGIC: &[u8; 4] = b"WVPC";
+
+#[derive(Debug)]
+pub enum EnvelopeError {
+    Malformed(&'static str),
+    UnsupportedVersion(u8),
+    /// The proof was made for another circuit than the vk's
+    CircuitMismatch,
+    KMismatch {
+        expected: u32,
+        found: u32,
+    },
+    InstanceCount {
+        expected: usize,
+        found: usize,
+    },
+    Verification(plonk::Error),
+}
+
+impl core::fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EnvelopeError::Malformed(msg) => write!(f, "malformed proof envelope: {}", msg),
+            EnvelopeError::UnsupportedVersion(v) => {
+                write!(f, "unsupported proof envelope version {}", v)
+            }
+            EnvelopeError::CircuitMismatch => write!(f, "proof is for another circuit"),
+            EnvelopeError::KMismatch { expected, found } => {
+                write!(f, "proof is for k = {}, expected {}", found, expected)
+            }
+            EnvelopeError::InstanceCount { expected, found } => {
+                write!(f, "proof has {} instances, got {}", expected, found)
+            }
+            EnvelopeError::Verification(e) => write!(f, "verification failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+/// Proof encodings this crate has used over time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofFormat {
+    /// A bare transcript, as in the original `proof.bin` fixture
+    Legacy,
+    /// A `ProofEnvelope` of the given version
+    Envelope(u8),
+    /// A compact `ProofEnvelope` of the given version
+    Compact(u8),
+}
+
+impl ProofFormat {
+    pub fn detect(bytes: &[u8]) -> Self {
+        match bytes {
+            [m0, m1, m2, m3, version, ..] if [*m0, *m1, *m2, *m3] == *ENVELOPE_MAGIC => {
+                ProofFormat::Envelope(*version)
+            }
+            [m0, m1, m2, m3, version, ..] if [*m0, *m1, *m2, *m3] == *COMPACT_MAGIC => {
+                ProofFormat::Compact(*version)
+            }
+            _ => ProofFormat::Legacy,
+        }
+    }
+}
+
+/// Decode a proof in any known format and upgrade it to the current envelope. Legacy
+/// proofs carry no header, so theirs is filled in from `vk` and `instance_count`.
+pub fn migrate(
+    bytes: &[u8],
+    vk: &VerifyingKey,
+    instance_count: usize,
+) -> Result<ProofEnvelope, EnvelopeError> {
+    match ProofFormat::detect(bytes) {
+        ProofFormat::Legacy => Ok(ProofEnvelope::new(
+            vk,
+            Proof::new(bytes.to_vec()),
+            instance_count,
+        )),
+        ProofFormat::Envelope(ENVELOPE_VERSION) => ProofEnvelope::from_bytes(bytes),
+        ProofFormat::Compact(ENVELOPE_VERSION) => ProofEnvelope::from_compact_bytes(bytes),
+        ProofFormat::Envelope(version) | ProofFormat::Compact(version) => {
+            Err(EnvelopeError::UnsupportedVersion(version))
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofEnvelope {
+    pub version: u8,
+    pub circuit_id: [u8; 32],
+    pub k: u32,
+    pub instance_count: u32,
+    pub proof: Proof,
+}
+
+impl ProofEnvelope {
+    pub fn new(vk: &VerifyingKey, proof: Proof, instance_count: usize) -> Self {
+        ProofEnvelope {
+            version: ENVELOPE_VERSION,
+            circuit_id: vk.digest(),
+            k: vk.k,
+            instance_count: instance_count as u32,
+            proof,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.proof.as_ref().len());
+        bytes.extend_from_slice(ENVELOPE_MAGIC);
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.circuit_id);
+        bytes.extend_from_slice(&self.k.to_le_bytes());
+        bytes.extend_from_slice(&self.instance_count.to_le_bytes());
+        bytes.extend_from_slice(self.proof.as_ref());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(EnvelopeError::Malformed("shorter than the header"));
+        }
+        let (header, proof) = bytes.split_at(HEADER_LEN);
+        if &header[..4] != ENVELOPE_MAGIC {
+            return Err(EnvelopeError::Malformed("bad magic"));
+        }
+        let version = header[4];
+        if version != ENVELOPE_VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(version));
+        }
+
+        Ok(ProofEnvelope {
+            version,
+            circuit_id: header[5..37].try_into().unwrap(),
+            k: u32::from_le_bytes(header[37..41].try_into().unwrap()),
+            instance_count: u32::from_le_bytes(header[41..45].try_into().unwrap()),
+            proof: Proof::new(proof.to_vec()),
+        })
+    }
+
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let proof = self.proof.as_ref();
+        let mut bytes = Vec::with_capacity(4 + 1 + 32 + 3 * 5 + proof.len());
+        bytes.extend_from_slice(COMPACT_MAGIC);
+        bytes.push(self.version);
+        bytes.extend_from_slice(&self.circuit_id);
+        write_varint(&mut bytes, self.k);
+        write_varint(&mut bytes, self.instance_count);
+        write_varint(&mut bytes, proof.len() as u32);
+        bytes.extend_from_slice(proof);
+        bytes
+    }
+
+    pub fn from_compact_bytes(bytes: &[u8]) -> Result<Self, EnvelopeError> {
+        if bytes.len() < 4 + 1 + 32 {
+            return Err(EnvelopeError::Malformed("shorter than the header"));
+        }
+        if &bytes[..4] != COMPACT_MAGIC {
+            return Err(EnvelopeError::Malformed("bad magic"));
+        }
+        let version = bytes[4];
+        if version != ENVELOPE_VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(version));
+        }
+        let circuit_id = bytes[5..37].try_into().unwrap();
+
+        let mut rest = &bytes[37..];
+        let k = read_varint(&mut rest)?;
+        let instance_count = read_varint(&mut rest)?;
+        let proof_len = read_varint(&mut rest)? as usize;
+        if rest.len() != proof_len {
+            return Err(EnvelopeError::Malformed("transcript length mismatch"));
+        }
+
+        Ok(ProofEnvelope {
+            version,
+            circuit_id,
+            k,
+            instance_count,
+            proof: Proof::new(rest.to_vec()),
+        })
+    }
+
+    /// Check the header against `vk` and `instances`, then verify the proof. The instance
+    /// count in the header is the total over all instance columns.
+    pub fn verify(
+        &self,
+        vk: &VerifyingKey,
+        instances: &[&[pallas::Base]],
+    ) -> Result<(), EnvelopeError> {
+        if self.circuit_id != vk.digest() {
+            return Err(EnvelopeError::CircuitMismatch);
+        }
+        if self.k != vk.k {
+            return Err(EnvelopeError::KMismatch {
+                expected: vk.k,
+                found: self.k,
+            });
+        }
+        let instance_count = instances.iter().map(|column| column.len()).sum();
+        if self.instance_count as usize != instance_count {
+            return Err(EnvelopeError::InstanceCount {
+                expected: self.instance_count as usize,
+                found: instance_count,
+            });
+        }
+        self.proof
+            .verify(vk, instances)
+            .map_err(EnvelopeError::Verification)
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u32, EnvelopeError> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or(EnvelopeError::Malformed("truncated varint"))?;
+        *bytes = rest;
+        let bits = u32::from(byte & 0x7f)
+            .checked_shl(shift)
+            .filter(|bits| bits >> shift == u32::from(byte & 0x7f))
+            .ok_or(EnvelopeError::Malformed("varint overflows u32"))?;
+        value |= bits;
+        if byte & 0x80 == 0 {
+            // A zero final byte only pads, which would give one value many encodings
+            if byte == 0 && shift > 0 {
+                return Err(EnvelopeError::Malformed("overlong varint"));
+            }
+            return Ok(value);
+        }
+    }
+    Err(EnvelopeError::Malformed("varint overflows u32"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint() {
+        for value in [0, 1, 127, 128, 300, u32::MAX] {
+            let mut bytes = vec![];
+            write_varint(&mut bytes, value);
+            let mut slice = &bytes[..];
+            assert_eq!(read_varint(&mut slice).unwrap(), value);
+            assert!(slice.is_empty());
+        }
+        assert!(read_varint(&mut &[0xff, 0xff, 0xff, 0xff, 0x7f][..]).is_err());
+        assert!(read_varint(&mut &[0x80, 0x00][..]).is_err());
+        assert!(read_varint(&mut &[0x81, 0x80, 0x00][..]).is_err());
+    }
+}
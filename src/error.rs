@@ -0,0 +1,118 @@
+use std::io;
+
+use halo2_proofs::plonk;
+
+use crate::{
+    envelope::EnvelopeError, hex::HexError, instances::InstanceError, statement::StatementError,
+    witness::WitnessError,
+};
+
+/// The crate's error type
+#[derive(Debug)]
+pub enum Error {
+    /// Keygen, proving or verification failed inside halo2
+    Plonk(plonk::Error),
+    Io(io::Error),
+    Instance(InstanceError),
+    Envelope(EnvelopeError),
+    Hex(HexError),
+    Statement(StatementError),
+    Witness(WitnessError),
+    /// The proof can't be for the vk, its length is off
+    ProofSize {
+        expected: usize,
+        found: usize,
+    },
+    InstanceColumns {
+        expected: usize,
+        found: usize,
+    },
+    /// `expected` is exact if the vk knows the circuit's instance lengths, a maximum if not
+    InstanceCount {
+        column: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A `cancel::CancelToken` was cancelled before verification finished
+    Cancelled,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Plonk(e) => write!(f, "plonk error: {}", e),
+            Error::Io(e) => write!(f, "io error: {}", e),
+            Error::Instance(e) => write!(f, "{}", e),
+            Error::Envelope(e) => write!(f, "{}", e),
+            Error::Hex(e) => write!(f, "{}", e),
+            Error::Statement(e) => write!(f, "{}", e),
+            Error::Witness(e) => write!(f, "{}", e),
+            Error::ProofSize { expected, found } => {
+                write!(f, "proof is {} bytes, expected {}", found, expected)
+            }
+            Error::InstanceColumns { expected, found } => {
+                write!(
+                    f,
+                    "got {} instance columns, circuit has {}",
+                    found, expected
+                )
+            }
+            Error::InstanceCount {
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "got {} instances in column {}, expected {}",
+                found, column, expected
+            ),
+            Error::Cancelled => write!(f, "verification was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<plonk::Error> for Error {
+    fn from(e: plonk::Error) -> Self {
+        Error::Plonk(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<InstanceError> for Error {
+    fn from(e: InstanceError) -> Self {
+        Error::Instance(e)
+    }
+}
+
+impl From<EnvelopeError> for Error {
+    fn from(e: EnvelopeError) -> Self {
+        Error::Envelope(e)
+    }
+}
+
+impl From<HexError> for Error {
+    fn from(e: HexError) -> Self {
+        Error::Hex(e)
+    }
+}
+
+impl From<StatementError> for Error {
+    fn from(e: StatementError) -> Self {
+        Error::Statement(e)
+    }
+}
+
+impl From<WitnessError> for Error {
+    fn from(e: WitnessError) -> Self {
+        Error::Witness(e)
+    }
+}
@@ -0,0 +1,177 @@
+//! C ABI for native hosts that embed the verifier without a wasm runtime, behind the `ffi`
+//! feature. See `include/wasm_verifier.h` for the declarations.
+//!
+//! Every function returns one of the `abi::STATUS_` codes. Instances are encoded with
+//! `encode_instances`. A vk is either this crate's own encoding (`VerifyingKey::to_bytes`,
+//! params included) or halo2's bare one, read against the params from `wv_load_params`
+//! or, without those, params generated for the arithmetic circuit's k. Panics are caught
+//! and reported as `STATUS_ERROR` rather than unwinding into the caller.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, OnceLock},
+};
+
+use halo2_proofs::{pasta::vesta, poly::commitment::Params};
+
+use crate::{
+    abi::{Verdict, STATUS_ERROR, STATUS_MALFORMED, STATUS_VALID},
+    params, verify_bytes, MyCircuit, VerifyingKey,
+};
+
+const K: u32 = 4;
+
+static LOADED_PARAMS: OnceLock<Arc<Params<vesta::Affine>>> = OnceLock::new();
+
+/// A `(ptr, len)` pair from C as a slice, allowing a null pointer for an empty buffer
+unsafe fn slice<'a>(ptr: *const u8, len: usize) -> &'a [u8] {
+    match len {
+        0 => &[],
+        _ => core::slice::from_raw_parts(ptr, len),
+    }
+}
+
+fn read_vk(bytes: &[u8]) -> Option<VerifyingKey> {
+    let vk = match bytes.starts_with(crate::VK_MAGIC) {
+        true => VerifyingKey::from_bytes(bytes, MyCircuit::default()),
+        false => {
+            let params = LOADED_PARAMS
+                .get()
+                .cloned()
+                .unwrap_or_else(|| params::shared(K));
+            VerifyingKey::from_halo2_bytes(bytes, params, MyCircuit::default())
+        }
+    };
+    vk.ok()
+        .map(|vk| vk.with_instance_lengths(MyCircuit::INSTANCE_LENGTHS.to_vec()))
+}
+
+fn guarded(f: impl FnOnce() -> u8) -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(STATUS_ERROR) as i32
+}
+
+/// Params serialized with `params::to_bytes`, used for every later halo2-encoded vk.
+/// Only the first call takes effect.
+///
+/// # Safety
+///
+/// `ptr` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wv_load_params(ptr: *const u8, len: usize) -> i32 {
+    let bytes = slice(ptr, len);
+    guarded(|| match params::from_bytes(bytes) {
+        Ok((_, params)) => match LOADED_PARAMS.set(Arc::new(params)) {
+            Ok(()) => STATUS_VALID,
+            Err(_) => STATUS_ERROR,
+        },
+        Err(_) => STATUS_MALFORMED,
+    })
+}
+
+/// Parse a vk once for many `wv_verify_with` calls. Returns null if it doesn't parse;
+/// free the key with `wv_vk_free`.
+///
+/// # Safety
+///
+/// `ptr` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn wv_vk_load(ptr: *const u8, len: usize) -> *mut VerifyingKey {
+    let bytes = slice(ptr, len);
+    match panic::catch_unwind(|| read_vk(bytes)) {
+        Ok(Some(vk)) => Box::into_raw(Box::new(vk)),
+        _ => core::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+///
+/// `vk` must come from `wv_vk_load` and not be freed already. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn wv_vk_free(vk: *mut VerifyingKey) {
+    if !vk.is_null() {
+        drop(Box::from_raw(vk));
+    }
+}
+
+/// Verify against a key from `wv_vk_load`
+///
+/// # Safety
+///
+/// `vk` must come from `wv_vk_load`, both buffers must be readable for their lengths.
+#[no_mangle]
+pub unsafe extern "C" fn wv_verify_with(
+    vk: *const VerifyingKey,
+    proof_ptr: *const u8,
+    proof_len: usize,
+    inst_ptr: *const u8,
+    inst_len: usize,
+) -> i32 {
+    let Some(vk) = vk.as_ref() else {
+        return STATUS_MALFORMED as i32;
+    };
+    let proof = slice(proof_ptr, proof_len);
+    let instances = slice(inst_ptr, inst_len);
+    guarded(|| Verdict::of(verify_bytes(vk, proof, instances)).status())
+}
+
+/// Parse the vk and verify in one call, for hosts that verify rarely
+///
+/// # Safety
+///
+/// All three buffers must be readable for their lengths.
+#[no_mangle]
+pub unsafe extern "C" fn wv_verify(
+    proof_ptr: *const u8,
+    proof_len: usize,
+    vk_ptr: *const u8,
+    vk_len: usize,
+    inst_ptr: *const u8,
+    inst_len: usize,
+) -> i32 {
+    let proof = slice(proof_ptr, proof_len);
+    let vk = slice(vk_ptr, vk_len);
+    let instances = slice(inst_ptr, inst_len);
+    guarded(|| match read_vk(vk) {
+        Some(vk) => Verdict::of(verify_bytes(&vk, proof, instances)).status(),
+        None => STATUS_MALFORMED,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::pasta::pallas;
+
+    use super::*;
+    use crate::{encode_instances, expected_instances};
+
+    #[test]
+    fn test_ffi_verify() {
+        let vk = VerifyingKey::build(K, &MyCircuit::default())
+            .unwrap()
+            .to_bytes();
+        let proof = include_bytes!("../proof.bin");
+        let instances = encode_instances(&expected_instances(
+            pallas::Base::from(69),
+            pallas::Base::from(42),
+        ));
+
+        let status = unsafe {
+            wv_verify(
+                proof.as_ptr(),
+                proof.len(),
+                vk.as_ptr(),
+                vk.len(),
+                instances.as_ptr(),
+                instances.len(),
+            )
+        };
+        assert_eq!(status, STATUS_VALID as i32);
+
+        let handle = unsafe { wv_vk_load(vk.as_ptr(), vk.len()) };
+        assert!(!handle.is_null());
+        let status =
+            unsafe { wv_verify_with(handle, proof.as_ptr(), proof.len(), core::ptr::null(), 0) };
+        assert_eq!(status, STATUS_MALFORMED as i32);
+        unsafe { wv_vk_free(handle) };
+    }
+}
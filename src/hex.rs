@@ -0,0 +1,66 @@
+//! Lowercase hex encoding, kept in-crate to avoid a dependency for a few lines
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HexError {
+    OddLength,
+    InvalidChar(char),
+}
+
+impl core::fmt::Display for HexError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HexError::OddLength => write!(f, "hex string has an odd length"),
+            HexError::InvalidChar(c) => write!(f, "invalid hex character {:?}", c),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+pub fn encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        out.push(DIGITS[(b >> 4) as usize] as char);
+        out.push(DIGITS[(b & 0xf) as usize] as char);
+    }
+    out
+}
+
+/// Accepts upper and lower case, with or without a `0x` prefix
+pub fn decode(s: &str) -> Result<Vec<u8>, HexError> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    // Also rules out multi-byte chars, so the rest can work on bytes
+    if let Some(c) = s.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(HexError::InvalidChar(c));
+    }
+    if s.len() % 2 != 0 {
+        return Err(HexError::OddLength);
+    }
+
+    fn nibble(b: u8) -> u8 {
+        (b as char).to_digit(16).unwrap() as u8
+    }
+
+    Ok(s.as_bytes()
+        .chunks_exact(2)
+        .map(|pair| (nibble(pair[0]) << 4) | nibble(pair[1]))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let bytes = [0x00, 0x7f, 0x80, 0xff];
+        assert_eq!(encode(&bytes), "007f80ff");
+        assert_eq!(decode("0x007F80ff").unwrap(), bytes);
+        assert_eq!(decode("abc"), Err(HexError::OddLength));
+        assert_eq!(decode("zz"), Err(HexError::InvalidChar('z')));
+        assert_eq!(decode("é"), Err(HexError::InvalidChar('é')));
+        assert_eq!(decode("abé"), Err(HexError::InvalidChar('é')));
+        assert_eq!(decode("ab\u{2003}"), Err(HexError::InvalidChar('\u{2003}')));
+    }
+}
@@ -0,0 +1,145 @@
+//! On-disk cache of compiled guest modules, so a restarted node deserializes its verifier
+//! modules instead of compiling each one again.
+//!
+//! Entries are named after a blake2b digest of the wasm bytes, the compiler, the wasmer
+//! version and the config baked into the artifact (metering and memory cap), and hold
+//! wasmer's serialized artifact. Once the directory outgrows `max_bytes`, the least
+//! recently used entries are removed; a hit refreshes the entry's modification time.
+//!
+//! Loading an entry runs native code from disk, so the directory must be as trusted as
+//! the node's own binary.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use tracing::debug;
+use wasmer::Module;
+
+use super::{build_engine, check_declared_limits, CompiledModule, HostConfig, HostError};
+use crate::hex;
+
+const EXTENSION: &str = "wasmu";
+
+#[derive(Clone, Debug)]
+pub struct ArtifactCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ArtifactCache {
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        ArtifactCache {
+            dir: dir.into(),
+            max_bytes,
+        }
+    }
+
+    /// `$XDG_CACHE_HOME/wasm-verifier/artifacts`, or `~/.cache/wasm-verifier/artifacts`
+    pub fn default_dir() -> Option<PathBuf> {
+        let cache_home = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+        Some(cache_home.join("wasm-verifier").join("artifacts"))
+    }
+
+    /// The entry for `wasm_bytes` compiled under `config`, or None if the artifact can't
+    /// be cached because a custom cost function can't be told apart from another
+    pub fn path(&self, wasm_bytes: &[u8], config: &HostConfig) -> Option<PathBuf> {
+        if config.cost_function.is_some() {
+            return None;
+        }
+        let key = format!(
+            "{}|{}|{:?}|{:?}",
+            config.compiler,
+            wasmer::VERSION,
+            config.gas_limit,
+            config.max_memory_pages
+        );
+        let mut state = blake2b_simd::Params::new()
+            .hash_length(32)
+            .personal(b"WasmVerifierArt_")
+            .to_state();
+        state.update(key.as_bytes()).update(wasm_bytes);
+        let digest = hex::encode(state.finalize().as_bytes());
+        Some(self.dir.join(format!("{}.{}", digest, EXTENSION)))
+    }
+
+    /// The module compiled from `wasm_bytes`, deserialized from the cache when possible
+    /// and compiled and stored otherwise. Failing to store it is not an error.
+    pub fn get_or_compile(
+        &self,
+        wasm_bytes: &[u8],
+        config: HostConfig,
+    ) -> Result<CompiledModule, HostError> {
+        let Some(path) = self.path(wasm_bytes, &config) else {
+            return CompiledModule::new(wasm_bytes, config);
+        };
+        if let Some(module) = self.load(&path, &config) {
+            return module;
+        }
+
+        let compiled = CompiledModule::new(wasm_bytes, config)?;
+        if let Err(e) = self.store(&path, &compiled) {
+            debug!(error = %e, "could not cache compiled module");
+        }
+        Ok(compiled)
+    }
+
+    /// A corrupt or stale entry reads as a miss and gets overwritten
+    fn load(&self, path: &Path, config: &HostConfig) -> Option<Result<CompiledModule, HostError>> {
+        let artifact = fs::read(path).ok()?;
+        let engine = build_engine(config);
+        // SAFETY: entries are written by `store` from modules compiled with this wasmer
+        // version and an engine configured as `engine`, both part of the entry's name
+        let module = unsafe { Module::deserialize(&engine, &artifact[..]) }.ok()?;
+        // Refresh the entry for eviction, a failure only makes it look older
+        if let Ok(file) = fs::File::options().write(true).open(path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(
+            check_declared_limits(&module, config).map(|()| CompiledModule {
+                engine,
+                module,
+                config: config.clone(),
+            }),
+        )
+    }
+
+    fn store(&self, path: &Path, compiled: &CompiledModule) -> io::Result<()> {
+        let artifact = compiled.module.serialize().map_err(io::Error::other)?;
+        fs::create_dir_all(&self.dir)?;
+
+        // Write then rename, so a concurrent reader never sees a partial entry
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, artifact)?;
+        fs::rename(tmp, path)?;
+        self.evict()
+    }
+
+    /// Remove the least recently used entries until the rest fit in `max_bytes`
+    pub fn evict(&self) -> io::Result<()> {
+        let mut entries = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |ext| ext != EXTENSION) {
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            entries.push((metadata.modified()?, metadata.len(), path));
+        }
+        entries.sort();
+
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+//! Stored verification timings to hold later builds against, so a change to the gadgets or
+//! the guest that slows verification down fails loudly instead of going unnoticed.
+//!
+//! A baseline maps a name, `native` or a compiler's, to the mean time of one verification.
+//! It is stored as a flat JSON object of microseconds, e.g. `{"native":2900}`.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use halo2_proofs::pasta::pallas;
+
+use super::{CompiledModule, Compiler, HostConfig, HostError};
+use crate::{encode_instances, Proof, VerifyingKey};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Baseline {
+    pub timings: BTreeMap<String, Duration>,
+}
+
+/// A timing slower than its baseline by more than the allowed ratio
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Regression {
+    pub name: String,
+    pub baseline: Duration,
+    pub current: Duration,
+}
+
+impl core::fmt::Display for Regression {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{}: {}us -> {}us ({:+.1}%)",
+            self.name,
+            self.baseline.as_micros(),
+            self.current.as_micros(),
+            (self.current.as_secs_f64() / self.baseline.as_secs_f64() - 1.0) * 100.0
+        )
+    }
+}
+
+impl Baseline {
+    /// Mean time over `runs` verifications of `proof`, natively against `vk` and in the
+    /// guest under every available compiler. The guest builds its own vk, outside the
+    /// timing, so `proof` has to be for the module's circuit.
+    pub fn measure(
+        wasm_bytes: &[u8],
+        vk: &VerifyingKey,
+        proof: &Proof,
+        instances: &[pallas::Base],
+        runs: u32,
+    ) -> Result<Self, HostError> {
+        let mut timings = BTreeMap::new();
+
+        let now = Instant::now();
+        for _ in 0..runs {
+            let _ = proof.verify(vk, &[instances]);
+        }
+        timings.insert("native".to_string(), now.elapsed() / runs);
+
+        let encoded = encode_instances(instances);
+        for compiler in Compiler::available() {
+            let config = HostConfig {
+                compiler,
+                ..Default::default()
+            };
+            let mut harness = CompiledModule::new(wasm_bytes, config)?.instantiate()?;
+            harness.warmup()?;
+
+            let now = Instant::now();
+            for _ in 0..runs {
+                if !harness.verify(proof.as_ref(), &encoded)?.is_valid() {
+                    return Err(HostError::Abi("guest rejected the proof".to_string()));
+                }
+            }
+            timings.insert(compiler.to_string(), now.elapsed() / runs);
+        }
+
+        Ok(Baseline { timings })
+    }
+
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> = self
+            .timings
+            .iter()
+            .map(|(name, elapsed)| format!("\"{}\":{}", name, elapsed.as_micros()))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+
+    /// Reads what `to_json` writes, None for anything else
+    pub fn from_json(json: &str) -> Option<Self> {
+        let body = json.trim().strip_prefix('{')?.strip_suffix('}')?.trim();
+        let mut timings = BTreeMap::new();
+        if body.is_empty() {
+            return Some(Baseline { timings });
+        }
+        for field in body.split(',') {
+            let (name, micros) = field.split_once(':')?;
+            let name = name.trim().strip_prefix('"')?.strip_suffix('"')?;
+            let micros: u64 = micros.trim().parse().ok()?;
+            timings.insert(name.to_string(), Duration::from_micros(micros));
+        }
+        Some(Baseline { timings })
+    }
+
+    /// Every timing in `current` more than `tolerance` (0.1 for 10%) slower than here.
+    /// Names only one side has, e.g. a compiler not in this build, are skipped.
+    pub fn compare(&self, current: &Baseline, tolerance: f64) -> Result<(), Vec<Regression>> {
+        let regressions: Vec<Regression> = self
+            .timings
+            .iter()
+            .filter_map(|(name, &baseline)| {
+                let &current = current.timings.get(name)?;
+                let limit = baseline.as_secs_f64() * (1.0 + tolerance);
+                (current.as_secs_f64() > limit).then(|| Regression {
+                    name: name.clone(),
+                    baseline,
+                    current,
+                })
+            })
+            .collect();
+        match regressions.is_empty() {
+            true => Ok(()),
+            false => Err(regressions),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_baseline_compare() {
+        let baseline = Baseline::from_json("{\"native\":1000,\"singlepass\":20000}").unwrap();
+        assert_eq!(
+            Baseline::from_json(&baseline.to_json()),
+            Some(baseline.clone())
+        );
+
+        let mut current = baseline.clone();
+        current
+            .timings
+            .insert("native".to_string(), Duration::from_micros(1050));
+        assert!(baseline.compare(&current, 0.1).is_ok());
+
+        current
+            .timings
+            .insert("singlepass".to_string(), Duration::from_micros(30000));
+        let regressions = baseline.compare(&current, 0.1).unwrap_err();
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].name, "singlepass");
+    }
+}
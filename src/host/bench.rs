@@ -0,0 +1,221 @@
+use std::time::{Duration, Instant};
+
+use super::{CompiledModule, Compiler, Harness, HostConfig, HostError};
+use crate::abi::Verdict;
+
+/// Guest memory around one call, in 64KiB pages. Wasm memory never shrinks, so `after`
+/// is the peak the instance has needed so far, the figure to size `max_memory_pages` by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryPages {
+    pub before: u32,
+    pub after: u32,
+}
+
+impl MemoryPages {
+    /// Pages the call itself added
+    pub fn grown(&self) -> u32 {
+        self.after - self.before
+    }
+}
+
+impl Harness {
+    /// `verify`, also reporting the guest memory before and after the call
+    pub fn verify_with_memory(
+        &mut self,
+        proof: &[u8],
+        instances: &[u8],
+    ) -> Result<(Verdict, MemoryPages), HostError> {
+        let before = self.memory_pages()?;
+        let verdict = self.verify(proof, instances)?;
+        let after = self.memory_pages()?;
+        Ok((verdict, MemoryPages { before, after }))
+    }
+}
+
+/// Timings of one entrypoint call under one compiler
+#[derive(Clone, Debug)]
+pub struct EngineTiming {
+    pub compiler: Compiler,
+    pub compile: Duration,
+    pub instantiate: Duration,
+    pub call: Duration,
+    pub memory: MemoryPages,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BenchReport {
+    pub entrypoint: String,
+    pub timings: Vec<EngineTiming>,
+}
+
+impl core::fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "{}:", self.entrypoint)?;
+        for t in &self.timings {
+            writeln!(
+                f,
+                "  {:<10} compile [{}ms] instantiate [{}ms] call [{}ms] memory [{} -> {} pages]",
+                t.compiler.to_string(),
+                t.compile.as_millis(),
+                t.instantiate.as_millis(),
+                t.call.as_millis(),
+                t.memory.before,
+                t.memory.after
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compile `wasm_bytes` under every available compiler and time a call to `entrypoint`
+pub fn compare_engines(wasm_bytes: &[u8], entrypoint: &str) -> Result<BenchReport, HostError> {
+    let mut report = BenchReport {
+        entrypoint: entrypoint.to_string(),
+        timings: vec![],
+    };
+
+    for compiler in Compiler::available() {
+        let config = HostConfig {
+            compiler,
+            ..Default::default()
+        };
+
+        let now = Instant::now();
+        let compiled = CompiledModule::new(wasm_bytes, config)?;
+        let compile = now.elapsed();
+
+        let now = Instant::now();
+        let mut harness = compiled.instantiate()?;
+        let instantiate = now.elapsed();
+
+        let before = harness.memory_pages()?;
+        let now = Instant::now();
+        harness.call(entrypoint)?;
+        let call = now.elapsed();
+        let after = harness.memory_pages()?;
+
+        report.timings.push(EngineTiming {
+            compiler,
+            compile,
+            instantiate,
+            call,
+            memory: MemoryPages { before, after },
+        });
+    }
+
+    Ok(report)
+}
+
+/// One k of `sweep`
+#[cfg(feature = "prover")]
+#[derive(Clone, Debug)]
+pub struct SweepRow {
+    pub k: u32,
+    pub proof_size: usize,
+    pub native_verify: Duration,
+    /// Guest verification under each available compiler, keygen excluded
+    pub wasm_verify: Vec<(Compiler, Duration)>,
+    /// Guest memory around the first verification after keygen, per compiler
+    pub wasm_memory: Vec<(Compiler, MemoryPages)>,
+}
+
+/// Prove the arithmetic circuit at every k in `ks` and time verifying it natively and in
+/// the guest, which is handed params of size k and so runs keygen at that size too. The
+/// circuit itself is fixed, every row past its own is padding: this shows how the cost
+/// and the virtualization penalty scale with the domain, not with the constraints.
+#[cfg(feature = "prover")]
+pub fn sweep(
+    wasm_bytes: &[u8],
+    ks: impl IntoIterator<Item = u32>,
+) -> Result<Vec<SweepRow>, HostError> {
+    use crate::{
+        encode_instances,
+        prover::{ArithWitness, Prover},
+    };
+    use halo2_proofs::{pasta::pallas, poly::commitment::Params};
+
+    const RUNS: u32 = 10;
+
+    let mut rows = vec![];
+    for k in ks {
+        let params = Params::new(k);
+        let srs = std::sync::Arc::new(crate::params::to_bytes(k, &params));
+        let prover = Prover::build_with_params(k, params.into())
+            .map_err(|e| HostError::Config(format!("keygen at k = {} failed: {}", k, e)))?;
+        let vk = prover.verifying_key();
+
+        let witness = ArithWitness {
+            a: pallas::Base::from(69),
+            b: pallas::Base::from(42),
+        };
+        let public_inputs = witness.expected_instances();
+        let proof = prover
+            .prove_deterministic(&witness, &public_inputs, [0; 32])
+            .map_err(|e| HostError::Config(format!("proving at k = {} failed: {}", k, e)))?;
+        let instances = encode_instances(&public_inputs);
+
+        let now = Instant::now();
+        for _ in 0..RUNS {
+            let _ = proof.verify(&vk, &[&public_inputs]);
+        }
+        let native_verify = now.elapsed() / RUNS;
+
+        let mut wasm_verify = vec![];
+        let mut wasm_memory = vec![];
+        for compiler in Compiler::available() {
+            let config = HostConfig {
+                compiler,
+                srs: Some(srs.clone()),
+                ..Default::default()
+            };
+            let mut harness = CompiledModule::new(wasm_bytes, config)?.instantiate()?;
+            harness.warmup()?;
+            let (_, memory) = harness.verify_with_memory(proof.as_ref(), &instances)?;
+            wasm_memory.push((compiler, memory));
+
+            let now = Instant::now();
+            for _ in 0..RUNS {
+                if !harness.verify(proof.as_ref(), &instances)?.is_valid() {
+                    return Err(HostError::Abi(format!(
+                        "guest rejected the k = {} proof",
+                        k
+                    )));
+                }
+            }
+            wasm_verify.push((compiler, now.elapsed() / RUNS));
+        }
+
+        rows.push(SweepRow {
+            k,
+            proof_size: proof.as_ref().len(),
+            native_verify,
+            wasm_verify,
+            wasm_memory,
+        });
+    }
+    Ok(rows)
+}
+
+/// `k,proof_bytes,native_us` then `<compiler>_us` and `<compiler>_pages` (peak guest
+/// memory) columns per compiler
+#[cfg(feature = "prover")]
+pub fn sweep_csv(rows: &[SweepRow]) -> String {
+    let mut csv = String::from("k,proof_bytes,native_us");
+    for compiler in Compiler::available() {
+        csv += &format!(",{}_us,{}_pages", compiler, compiler);
+    }
+    csv += "\n";
+    for row in rows {
+        csv += &format!(
+            "{},{},{}",
+            row.k,
+            row.proof_size,
+            row.native_verify.as_micros()
+        );
+        for ((_, elapsed), (_, memory)) in row.wasm_verify.iter().zip(&row.wasm_memory) {
+            csv += &format!(",{},{}", elapsed.as_micros(), memory.after);
+        }
+        csv += "\n";
+    }
+    csv
+}
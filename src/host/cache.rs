@@ -0,0 +1,158 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::abi::Verdict;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub proof: [u8; 32],
+    pub instances: [u8; 32],
+    pub circuit_id: String,
+}
+
+impl CacheKey {
+    pub fn new(circuit_id: &str, proof: &[u8], instances: &[u8]) -> Self {
+        CacheKey {
+            proof: digest(proof),
+            instances: digest(instances),
+            circuit_id: circuit_id.to_string(),
+        }
+    }
+}
+
+fn digest(bytes: &[u8]) -> [u8; 32] {
+    let hash = blake2b_simd::Params::new().hash_length(32).hash(bytes);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(hash.as_bytes());
+    out
+}
+
+/// Least-recently-used map from verification requests to their verdicts, so repeated
+/// requests (e.g. during block re-execution) don't re-enter wasm
+///
+/// Every use stamps the entry with a new generation and queues it again, leaving the old
+/// queue slot stale, so promotion is O(1). Stale slots are skipped on eviction and swept
+/// out once they outnumber the live ones.
+pub struct VerificationCache {
+    capacity: usize,
+    verdicts: HashMap<CacheKey, (Verdict, u64)>,
+    // Front is least recently used, as of each entry's last generation
+    order: VecDeque<(u64, CacheKey)>,
+    generation: u64,
+}
+
+impl VerificationCache {
+    pub fn new(capacity: usize) -> Self {
+        VerificationCache {
+            capacity,
+            verdicts: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            generation: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.verdicts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verdicts.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.verdicts.clear();
+        self.order.clear();
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<Verdict> {
+        let generation = self.next_generation();
+        let (verdict, stamp) = self.verdicts.get_mut(key)?;
+        *stamp = generation;
+        let verdict = verdict.clone();
+        self.enqueue(generation, key.clone());
+        Some(verdict)
+    }
+
+    pub fn insert(&mut self, key: CacheKey, verdict: Verdict) {
+        if self.capacity == 0 {
+            return;
+        }
+        let generation = self.next_generation();
+        let fresh = self
+            .verdicts
+            .insert(key.clone(), (verdict, generation))
+            .is_none();
+        self.enqueue(generation, key);
+        if fresh && self.verdicts.len() > self.capacity {
+            self.evict_oldest();
+        }
+    }
+
+    fn next_generation(&mut self) -> u64 {
+        self.generation += 1;
+        self.generation
+    }
+
+    fn enqueue(&mut self, generation: u64, key: CacheKey) {
+        self.order.push_back((generation, key));
+        if self.order.len() > 2 * self.capacity.max(1) {
+            let verdicts = &self.verdicts;
+            self.order
+                .retain(|(generation, key)| verdicts.get(key).map(|(_, g)| g) == Some(generation));
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        while let Some((generation, key)) = self.order.pop_front() {
+            if self.verdicts.get(&key).map(|(_, g)| *g) == Some(generation) {
+                self.verdicts.remove(&key);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = VerificationCache::new(2);
+        let a = CacheKey::new("arith", b"a", b"");
+        let b = CacheKey::new("arith", b"b", b"");
+        let c = CacheKey::new("arith", b"c", b"");
+
+        cache.insert(a.clone(), Verdict::Valid);
+        cache.insert(b.clone(), Verdict::Valid);
+        assert_eq!(cache.get(&a), Some(Verdict::Valid));
+
+        // b is now the least recently used
+        cache.insert(c.clone(), Verdict::Valid);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&b), None);
+        assert!(cache.get(&a).is_some() && cache.get(&c).is_some());
+    }
+
+    #[test]
+    fn test_lru_many_hits() {
+        let mut cache = VerificationCache::new(2);
+        let a = CacheKey::new("arith", b"a", b"");
+        let b = CacheKey::new("arith", b"b", b"");
+        cache.insert(a.clone(), Verdict::Valid);
+        cache.insert(b.clone(), Verdict::Valid);
+
+        // Stale queue slots are swept, not left to grow with every hit
+        for _ in 0..100 {
+            assert!(cache.get(&a).is_some());
+        }
+        assert!(cache.order.len() <= 4);
+
+        cache.insert(CacheKey::new("arith", b"c", b""), Verdict::Valid);
+        assert_eq!(cache.get(&b), None);
+        assert!(cache.get(&a).is_some());
+    }
+}
@@ -0,0 +1,103 @@
+use wasmer::{
+    imports, AsStoreMut, AsStoreRef, Function, FunctionEnv, FunctionEnvMut, Imports, Instance,
+    Memory, MemoryView,
+};
+
+use super::perf::PerfMark;
+
+// Error codes returned to the guest, negative like darkfi's runtime errors
+const ERR_OBJECT_NOT_FOUND: i64 = -1;
+const ERR_MEMORY_ACCESS: i64 = -2;
+
+/// Longer `drk_log` lines are cut to this many bytes
+const MAX_LOG_LINE: u32 = 1024;
+/// `drk_log` lines past this many are dropped, so a guest can't grow the host's memory
+const MAX_LOG_LINES: usize = 1024;
+
+/// Host state behind the darkfi-style imports
+#[derive(Default)]
+pub struct DarkfiEnv {
+    /// Set once the instance exists, every import needs it to reach guest memory
+    pub memory: Option<Memory>,
+    /// Lines logged by the guest through `drk_log`, up to `MAX_LOG_LINES` of at most
+    /// `MAX_LOG_LINE` bytes
+    pub logs: Vec<String>,
+    /// Objects the guest can fetch by index through `get_object_size`/`get_object_bytes`
+    pub objects: Vec<Vec<u8>>,
+    /// The last buffer passed to `set_return_data`
+    pub return_data: Option<Vec<u8>>,
+    /// Marks recorded through `wv_perf_mark`, see host::perf
+    pub perf_marks: Vec<PerfMark>,
+    /// The instance itself if it is metered, for `wv_perf_mark` to read its gas
+    pub(super) metered_instance: Option<Instance>,
+}
+
+impl DarkfiEnv {
+    fn view<'a>(&self, store: &'a impl AsStoreRef) -> Option<MemoryView<'a>> {
+        self.memory.as_ref().map(|memory| memory.view(store))
+    }
+}
+
+/// The subset of darkfi's contract runtime imports (namespace `env`) that a verifier
+/// section needs: logging, object access and return data
+pub fn darkfi_imports(store: &mut impl AsStoreMut, env: &FunctionEnv<DarkfiEnv>) -> Imports {
+    imports! {
+        "env" => {
+            "drk_log" => Function::new_typed_with_env(store, env, drk_log),
+            "get_object_size" => Function::new_typed_with_env(store, env, get_object_size),
+            "get_object_bytes" => Function::new_typed_with_env(store, env, get_object_bytes),
+            "set_return_data" => Function::new_typed_with_env(store, env, set_return_data),
+        }
+    }
+}
+
+/// `len` bytes of guest memory at `ptr`, or None if they're out of bounds. Checked before
+/// allocating, `len` comes from the guest.
+fn read_guest(env: &mut FunctionEnvMut<DarkfiEnv>, ptr: u32, len: u32) -> Option<Vec<u8>> {
+    let (data, store) = env.data_and_store_mut();
+    let view = data.view(&store)?;
+    if ptr as u64 + len as u64 > view.data_size() {
+        return None;
+    }
+    let mut buf = vec![0u8; len as usize];
+    view.read(ptr as u64, &mut buf).ok()?;
+    Some(buf)
+}
+
+fn drk_log(mut env: FunctionEnvMut<DarkfiEnv>, ptr: u32, len: u32) {
+    if env.data().logs.len() >= MAX_LOG_LINES {
+        return;
+    }
+    if let Some(buf) = read_guest(&mut env, ptr, len.min(MAX_LOG_LINE)) {
+        let line = String::from_utf8_lossy(&buf).into_owned();
+        env.data_mut().logs.push(line);
+    }
+}
+
+fn get_object_size(env: FunctionEnvMut<DarkfiEnv>, idx: u32) -> i64 {
+    match env.data().objects.get(idx as usize) {
+        Some(object) => object.len() as i64,
+        None => ERR_OBJECT_NOT_FOUND,
+    }
+}
+
+fn get_object_bytes(mut env: FunctionEnvMut<DarkfiEnv>, ptr: u32, idx: u32) -> i64 {
+    let (data, store) = env.data_and_store_mut();
+    let Some(object) = data.objects.get(idx as usize) else {
+        return ERR_OBJECT_NOT_FOUND;
+    };
+    match data.view(&store).map(|view| view.write(ptr as u64, object)) {
+        Some(Ok(())) => 0,
+        _ => ERR_MEMORY_ACCESS,
+    }
+}
+
+fn set_return_data(mut env: FunctionEnvMut<DarkfiEnv>, ptr: u32, len: u32) -> i64 {
+    match read_guest(&mut env, ptr, len) {
+        Some(buf) => {
+            env.data_mut().return_data = Some(buf);
+            0
+        }
+        None => ERR_MEMORY_ACCESS,
+    }
+}
@@ -0,0 +1,794 @@
+//! Host side of the wasm verifier: compiles the guest module with wasmer and
+//! calls its entrypoints.
+
+mod artifacts;
+pub mod baseline;
+pub mod bench;
+mod cache;
+mod darkfi;
+mod perf;
+mod pool;
+mod registry;
+mod runtime;
+mod tunables;
+#[cfg(feature = "wasmi")]
+pub mod wasmi;
+
+use std::{sync::Arc, time::Instant};
+
+use tracing::{debug, info_span, trace};
+
+use wasmer::{
+    wasmparser::Operator, BaseTunables, CompilerConfig, Engine, ExternType, FunctionEnv, Instance,
+    Module, Pages, Store, Target,
+};
+#[cfg(feature = "cranelift")]
+use wasmer_compiler_cranelift::Cranelift;
+use wasmer_compiler_singlepass::Singlepass;
+
+use crate::{
+    abi::{self, Verdict},
+    Proof,
+};
+use wasmer_middlewares::{
+    metering::{get_remaining_points, set_remaining_points, MeteringPoints},
+    Metering,
+};
+
+pub use artifacts::ArtifactCache;
+pub use cache::{CacheKey, VerificationCache};
+pub use darkfi::{darkfi_imports, DarkfiEnv};
+pub use perf::{GuestPerfReport, PerfMark, PhaseCost};
+pub use pool::VerifierPool;
+pub use registry::ModuleRegistry;
+pub use runtime::WasmRuntime;
+pub use tunables::LimitingTunables;
+
+/// Default per-call budget, generous enough for `entrypoint` at k = 4
+pub const DEFAULT_GAS_LIMIT: u64 = 10_000_000_000;
+
+/// Compute units a Solana transaction may use at most
+pub const SOLANA_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+
+#[derive(Debug)]
+pub enum HostError {
+    Compile(wasmer::CompileError),
+    Deserialize(wasmer::DeserializeError),
+    /// The requested configuration can't be honoured
+    Config(String),
+    /// No module is registered for this circuit id
+    UnknownCircuit(String),
+    Instantiation(Box<wasmer::InstantiationError>),
+    Export(wasmer::ExportError),
+    Runtime(wasmer::RuntimeError),
+    Memory(wasmer::MemoryAccessError),
+    MemoryGrow(wasmer::MemoryError),
+    /// The guest returned a result buffer that does not follow the ABI
+    Abi(String),
+    /// Errors from the wasmi backend, which don't map onto wasmer's
+    Interpreter(String),
+    /// The guest ran out of gas before returning
+    OutOfGas,
+    /// The guest memory would exceed `HostConfig::max_memory_pages`
+    MemoryLimit {
+        limit: u32,
+    },
+    /// A guest table would exceed `HostConfig::max_table_elements`
+    TableLimit {
+        requested: u32,
+        limit: u32,
+    },
+}
+
+impl core::fmt::Display for HostError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HostError::Compile(e) => write!(f, "compile error: {}", e),
+            HostError::Deserialize(e) => write!(f, "artifact deserialization error: {}", e),
+            HostError::Config(msg) => write!(f, "invalid host configuration: {}", msg),
+            HostError::UnknownCircuit(id) => write!(f, "no module registered for circuit {}", id),
+            HostError::Instantiation(e) => write!(f, "instantiation error: {}", e),
+            HostError::Export(e) => write!(f, "export error: {}", e),
+            HostError::Runtime(e) => write!(f, "runtime error: {}", e),
+            HostError::Memory(e) => write!(f, "guest memory access error: {}", e),
+            HostError::MemoryGrow(e) => write!(f, "guest memory grow error: {}", e),
+            HostError::Abi(msg) => write!(f, "guest ABI violation: {}", msg),
+            HostError::Interpreter(msg) => write!(f, "interpreter error: {}", msg),
+            HostError::OutOfGas => write!(f, "guest exceeded its gas limit"),
+            HostError::MemoryLimit { limit } => {
+                write!(f, "guest exceeded its memory limit of {} pages", limit)
+            }
+            HostError::TableLimit { requested, limit } => write!(
+                f,
+                "guest table of {} elements exceeds the limit of {}",
+                requested, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HostError {}
+
+impl From<wasmer::CompileError> for HostError {
+    fn from(e: wasmer::CompileError) -> Self {
+        HostError::Compile(e)
+    }
+}
+
+impl From<wasmer::DeserializeError> for HostError {
+    fn from(e: wasmer::DeserializeError) -> Self {
+        HostError::Deserialize(e)
+    }
+}
+
+impl From<wasmer::InstantiationError> for HostError {
+    fn from(e: wasmer::InstantiationError) -> Self {
+        HostError::Instantiation(Box::new(e))
+    }
+}
+
+impl From<wasmer::ExportError> for HostError {
+    fn from(e: wasmer::ExportError) -> Self {
+        HostError::Export(e)
+    }
+}
+
+impl From<wasmer::MemoryAccessError> for HostError {
+    fn from(e: wasmer::MemoryAccessError) -> Self {
+        HostError::Memory(e)
+    }
+}
+
+impl From<wasmer::MemoryError> for HostError {
+    fn from(e: wasmer::MemoryError) -> Self {
+        HostError::MemoryGrow(e)
+    }
+}
+
+impl From<wasmer::RuntimeError> for HostError {
+    fn from(e: wasmer::RuntimeError) -> Self {
+        HostError::Runtime(e)
+    }
+}
+
+/// Which wasmer compiler backs the engine
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compiler {
+    /// Fast compilation, slower code. What darkfi uses.
+    #[default]
+    Singlepass,
+    #[cfg(feature = "cranelift")]
+    Cranelift,
+}
+
+impl Compiler {
+    /// Every compiler enabled in this build
+    pub fn available() -> Vec<Compiler> {
+        vec![
+            Compiler::Singlepass,
+            #[cfg(feature = "cranelift")]
+            Compiler::Cranelift,
+        ]
+    }
+}
+
+impl core::fmt::Display for Compiler {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Compiler::Singlepass => write!(f, "singlepass"),
+            #[cfg(feature = "cranelift")]
+            Compiler::Cranelift => write!(f, "cranelift"),
+        }
+    }
+}
+
+/// Gas charged for each wasm operator, letting chains price in-wasm verification with
+/// their own per-opcode weights
+#[derive(Clone)]
+pub struct CostFunction(Arc<dyn Fn(&Operator) -> u64 + Send + Sync>);
+
+impl CostFunction {
+    pub fn new(f: impl Fn(&Operator) -> u64 + Send + Sync + 'static) -> Self {
+        CostFunction(Arc::new(f))
+    }
+
+    pub fn cost(&self, operator: &Operator) -> u64 {
+        (self.0)(operator)
+    }
+
+    /// Rough Solana compute units. SBF charges one unit per instruction and wasm's 64-bit
+    /// operators mostly lower to one each; structured control flow emits no code.
+    pub fn solana() -> Self {
+        CostFunction::new(|operator| match operator {
+            Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::End
+            | Operator::Else
+            | Operator::Nop => 0,
+            _ => 1,
+        })
+    }
+}
+
+impl core::fmt::Debug for CostFunction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "CostFunction")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HostConfig {
+    pub compiler: Compiler,
+    /// Gas available to each guest call. `None` disables metering altogether.
+    pub gas_limit: Option<u64>,
+    /// Per-operator gas cost. `None` charges one unit per operator.
+    pub cost_function: Option<CostFunction>,
+    /// Cap on guest linear memory, in 64KiB wasm pages
+    pub max_memory_pages: Option<u32>,
+    /// Cap on the number of elements of each guest table
+    pub max_table_elements: Option<u32>,
+    /// Serialized params handed to every instance through `load_params`, on its first
+    /// call rather than at instantiation
+    pub srs: Option<Arc<Vec<u8>>>,
+}
+
+impl Default for HostConfig {
+    fn default() -> Self {
+        HostConfig {
+            compiler: Compiler::default(),
+            gas_limit: Some(DEFAULT_GAS_LIMIT),
+            cost_function: None,
+            max_memory_pages: None,
+            max_table_elements: None,
+            srs: None,
+        }
+    }
+}
+
+/// A guest module compiled once, from which any number of harnesses can be instantiated
+#[derive(Clone)]
+pub struct CompiledModule {
+    engine: Engine,
+    module: Module,
+    config: HostConfig,
+}
+
+impl CompiledModule {
+    pub fn new(wasm_bytes: &[u8], config: HostConfig) -> Result<Self, HostError> {
+        let _span =
+            info_span!("compile", compiler = %config.compiler, bytes = wasm_bytes.len()).entered();
+        let now = Instant::now();
+
+        let engine = build_engine(&config);
+        let module = Module::new(&engine, wasm_bytes)?;
+        check_declared_limits(&module, &config)?;
+        debug!(
+            elapsed_ms = now.elapsed().as_millis() as u64,
+            "compiled module"
+        );
+
+        Ok(CompiledModule {
+            engine,
+            module,
+            config,
+        })
+    }
+
+    /// Load the verifier module that `build.rs` compiled ahead of time, skipping compilation.
+    /// The artifact was built with singlepass and metering, so `config` must ask for both.
+    #[cfg(feature = "aot")]
+    pub fn precompiled(config: HostConfig) -> Result<Self, HostError> {
+        if config.compiler != Compiler::Singlepass
+            || config.gas_limit.is_none()
+            || config.cost_function.is_some()
+        {
+            return Err(HostError::Config(
+                "the AOT artifact requires singlepass with default metering".to_string(),
+            ));
+        }
+
+        let _span = info_span!("load_precompiled").entered();
+        let artifact = include_bytes!(concat!(env!("OUT_DIR"), "/verifier.wasmu"));
+        let engine = build_engine(&config);
+        // SAFETY: the artifact was serialized by build.rs with the same wasmer version and
+        // the same compiler configuration as `engine`
+        let module = unsafe { Module::deserialize(&engine, &artifact[..])? };
+        check_declared_limits(&module, &config)?;
+
+        Ok(CompiledModule {
+            engine,
+            module,
+            config,
+        })
+    }
+
+    /// Names of the module's exports
+    pub fn exports(&self) -> Vec<String> {
+        self.module
+            .exports()
+            .map(|export| export.name().to_string())
+            .collect()
+    }
+
+    /// The circuit id from the module's metadata section, if it has one
+    pub fn circuit_id(&self) -> Option<String> {
+        let section = self
+            .module
+            .custom_sections(abi::CIRCUIT_ID_SECTION)
+            .next()?;
+        String::from_utf8(section.to_vec()).ok()
+    }
+
+    /// Instantiate and overwrite the fresh guest memory with a warmed-up snapshot
+    pub fn instantiate_from(&self, snapshot: &Snapshot) -> Result<Harness, HostError> {
+        let mut harness = self.instantiate()?;
+        harness.restore(snapshot)?;
+        Ok(harness)
+    }
+
+    pub fn instantiate(&self) -> Result<Harness, HostError> {
+        let _span = info_span!("instantiate").entered();
+        let now = Instant::now();
+
+        let mut store = Store::new(self.engine.clone());
+        // Modules that don't import anything simply ignore these
+        let env = FunctionEnv::new(&mut store, DarkfiEnv::default());
+        let mut import_object = darkfi_imports(&mut store, &env);
+        perf::define_imports(&mut import_object, &mut store, &env);
+        let instance = Instance::new(&mut store, &self.module, &import_object)?;
+
+        let memory = instance.exports.get_memory("memory")?.clone();
+        env.as_mut(&mut store).memory = Some(memory);
+        if self.config.gas_limit.is_some() {
+            env.as_mut(&mut store).metered_instance = Some(instance.clone());
+        }
+
+        let harness = Harness {
+            store,
+            instance,
+            env,
+            pending_srs: self.config.srs.clone(),
+            config: self.config.clone(),
+        };
+        debug!(
+            elapsed_ms = now.elapsed().as_millis() as u64,
+            "instantiated module"
+        );
+        Ok(harness)
+    }
+}
+
+/// A copy of a guest's linear memory. Rust guests keep all their state (statics, heap)
+/// in linear memory and the stack pointer is back at its initial value between calls, so
+/// restoring memory alone reproduces a warmed instance, e.g. one that already built its vk.
+#[derive(Clone)]
+pub struct Snapshot {
+    memory: Vec<u8>,
+    params_loaded: bool,
+}
+
+impl Snapshot {
+    pub fn len(&self) -> usize {
+        self.memory.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_empty()
+    }
+}
+
+/// A buffer living in guest linear memory, obtained from the guest's `alloc` export
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GuestBuf {
+    pub ptr: u32,
+    pub len: u32,
+}
+
+pub struct Harness {
+    store: Store,
+    instance: Instance,
+    env: FunctionEnv<DarkfiEnv>,
+    /// `config.srs` until it is handed to the guest
+    pending_srs: Option<Arc<Vec<u8>>>,
+    config: HostConfig,
+}
+
+impl Harness {
+    pub fn new(wasm_bytes: &[u8], config: HostConfig) -> Result<Self, HostError> {
+        CompiledModule::new(wasm_bytes, config)?.instantiate()
+    }
+
+    /// Current size of the guest linear memory, in 64KiB pages
+    pub fn memory_pages(&self) -> Result<u32, HostError> {
+        let memory = self.instance.exports.get_memory("memory")?;
+        Ok(memory.view(&self.store).size().0)
+    }
+
+    /// Copy the whole guest memory. Only valid between calls.
+    pub fn snapshot(&self) -> Result<Snapshot, HostError> {
+        let memory = self.instance.exports.get_memory("memory")?;
+        let view = memory.view(&self.store);
+        let mut bytes = vec![0u8; view.data_size() as usize];
+        view.read(0, &mut bytes)?;
+        Ok(Snapshot {
+            memory: bytes,
+            params_loaded: self.pending_srs.is_none(),
+        })
+    }
+
+    /// Grow the guest memory to the snapshot's size and copy the snapshot over it
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), HostError> {
+        let memory = self.instance.exports.get_memory("memory")?.clone();
+        let current = memory.view(&self.store).data_size();
+        let wanted = snapshot.memory.len() as u64;
+        if wanted > current {
+            let delta = (wanted - current) / wasmer::WASM_PAGE_SIZE as u64;
+            memory.grow(&mut self.store, Pages(delta as u32))?;
+        }
+        memory.view(&self.store).write(0, &snapshot.memory)?;
+        self.pending_srs = match snapshot.params_loaded {
+            true => None,
+            false => self.config.srs.clone(),
+        };
+        Ok(())
+    }
+
+    /// Do the params and vk work up front instead of on the first verification: hand
+    /// over `config.srs` if set, then run keygen through `build_vk`. Instances are cheap
+    /// until then, so a host can instantiate every module and warm only those it uses.
+    pub fn warmup(&mut self) -> Result<(), HostError> {
+        self.call("build_vk")
+    }
+
+    fn load_pending_params(&mut self) -> Result<(), HostError> {
+        match self.pending_srs.take() {
+            Some(srs) => self.inject_params(&srs),
+            None => Ok(()),
+        }
+    }
+
+    /// State shared with the darkfi imports: logs, objects and return data
+    pub fn darkfi_env(&self) -> &DarkfiEnv {
+        self.env.as_ref(&self.store)
+    }
+
+    pub fn darkfi_env_mut(&mut self) -> &mut DarkfiEnv {
+        self.env.as_mut(&mut self.store)
+    }
+
+    /// Call a no-argument export, refilling the gas budget first
+    pub fn call(&mut self, name: &str) -> Result<(), HostError> {
+        // `build_vk` is where the guest runs keygen
+        let _span = info_span!("call", export = name).entered();
+        self.load_pending_params()?;
+        let now = Instant::now();
+
+        self.refill_gas();
+        let function = self.instance.exports.get_function(name)?;
+        let result = function.call(&mut self.store, &[]);
+        let result = self.check_trap(result.map(|_| ()));
+        self.trace_call(now);
+        result
+    }
+
+    /// Verify `proof` against `instances` (see `encode_instances`) through the guest's memory ABI
+    pub fn verify(&mut self, proof: &[u8], instances: &[u8]) -> Result<Verdict, HostError> {
+        let _span = info_span!(
+            "verify",
+            proof_bytes = proof.len(),
+            instance_bytes = instances.len()
+        )
+        .entered();
+        let verdict = runtime::verify_through_abi(self, proof, instances)?;
+        trace!(
+            monotonic_counter.verifications = 1_u64,
+            valid = verdict.is_valid()
+        );
+        Ok(verdict)
+    }
+
+    /// Copy `bytes` into a fresh guest allocation
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<GuestBuf, HostError> {
+        let len = u32::try_from(bytes.len())
+            .map_err(|_| HostError::Abi("buffer does not fit in guest memory".to_string()))?;
+        // A previous call may have left the meter exhausted
+        self.refill_gas();
+        let alloc = self
+            .instance
+            .exports
+            .get_typed_function::<u32, u32>(&self.store, "alloc")?;
+        let ptr = alloc.call(&mut self.store, len)?;
+
+        let memory = self.instance.exports.get_memory("memory")?;
+        memory.view(&self.store).write(ptr as u64, bytes)?;
+        Ok(GuestBuf { ptr, len })
+    }
+
+    /// Copy a guest buffer back out, leaving it allocated
+    pub fn read_bytes(&self, buf: GuestBuf) -> Result<Vec<u8>, HostError> {
+        let mut bytes = vec![0u8; buf.len as usize];
+        let memory = self.instance.exports.get_memory("memory")?;
+        memory.view(&self.store).read(buf.ptr as u64, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Call the guest's `verify` export on buffers already in guest memory and return the
+    /// packed result pointer
+    pub fn call_verify(&mut self, proof: GuestBuf, instances: GuestBuf) -> Result<u64, HostError> {
+        self.load_pending_params()?;
+        let now = Instant::now();
+        self.refill_gas();
+        let verify = self
+            .instance
+            .exports
+            .get_typed_function::<(u32, u32, u32, u32), u64>(&self.store, "verify")?;
+        let result = verify.call(
+            &mut self.store,
+            proof.ptr,
+            proof.len,
+            instances.ptr,
+            instances.len,
+        );
+        let packed = self.check_trap(result);
+        self.trace_call(now);
+        packed
+    }
+
+    /// Run a darkfi contract section (`__initialize`, `__metadata`, `__entrypoint` or
+    /// `__update`) on `payload`, see darkfi.rs for the input layout. Returns the section's
+    /// code; return data lands in `darkfi_env().return_data`.
+    pub fn call_section(
+        &mut self,
+        name: &str,
+        contract_id: [u8; 32],
+        payload: &[u8],
+    ) -> Result<u64, HostError> {
+        let _span = info_span!("call_section", section = name).entered();
+        self.load_pending_params()?;
+        let mut input = contract_id.to_vec();
+        input.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        input.extend_from_slice(payload);
+        let buf = self.write_bytes(&input)?;
+
+        let now = Instant::now();
+        self.refill_gas();
+        let section = self
+            .instance
+            .exports
+            .get_typed_function::<u32, u64>(&self.store, name)?;
+        let result = section.call(&mut self.store, buf.ptr);
+        let code = self.check_trap(result);
+        self.trace_call(now);
+        self.free(buf)?;
+        code
+    }
+
+    /// Prove inside the guest through its `prove` export, which needs a module built with
+    /// the `prover` feature. `witness` is encoded for `ArithWitness::from_bytes`, `instances`
+    /// with `encode_instances`.
+    pub fn prove(
+        &mut self,
+        witness: &[u8],
+        instances: &[u8],
+        seed: [u8; 32],
+    ) -> Result<Proof, HostError> {
+        let _span = info_span!("prove").entered();
+        self.load_pending_params()?;
+        let now = Instant::now();
+        let bufs = [
+            self.write_bytes(witness)?,
+            self.write_bytes(instances)?,
+            self.write_bytes(&seed)?,
+        ];
+        self.refill_gas();
+        let prove = self
+            .instance
+            .exports
+            .get_typed_function::<(u32, u32, u32, u32, u32, u32), u64>(&self.store, "prove")?;
+        let result = prove.call(
+            &mut self.store,
+            bufs[0].ptr,
+            bufs[0].len,
+            bufs[1].ptr,
+            bufs[1].len,
+            bufs[2].ptr,
+            bufs[2].len,
+        );
+        let packed = self.check_trap(result)?;
+        self.trace_call(now);
+        for buf in bufs {
+            self.free(buf)?;
+        }
+
+        let (ptr, len) = abi::unpack(packed);
+        let result = GuestBuf { ptr, len };
+        let bytes = self.read_bytes(result)?;
+        self.free(result)?;
+        match bytes.split_first() {
+            Some((&abi::STATUS_VALID, proof)) => Ok(Proof::new(proof.to_vec())),
+            _ => match Verdict::decode(&bytes) {
+                Some(verdict) => Err(HostError::Abi(format!(
+                    "guest could not prove: {:?}",
+                    verdict
+                ))),
+                None => Err(HostError::Abi("malformed result buffer".to_string())),
+            },
+        }
+    }
+
+    /// Hand serialized params to the guest, which uses them instead of generating its own.
+    /// The guest takes params once, so this replaces any pending `config.srs`.
+    pub fn inject_params(&mut self, params: &[u8]) -> Result<(), HostError> {
+        let _span = info_span!("inject_params", bytes = params.len()).entered();
+        self.pending_srs = None;
+        let buf = self.write_bytes(params)?;
+        self.refill_gas();
+        let load_params = self
+            .instance
+            .exports
+            .get_typed_function::<(u32, u32), u32>(&self.store, "load_params")?;
+        let result = load_params.call(&mut self.store, buf.ptr, buf.len);
+        let status = self.check_trap(result)?;
+        self.free(buf)?;
+
+        if status != abi::STATUS_VALID as u32 {
+            return Err(HostError::Abi("guest rejected the params".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Hand the guest a vk in halo2's bare encoding, e.g. generated by other tooling, to
+    /// verify against instead of its built-in one
+    pub fn load_vk(&mut self, vk: &[u8]) -> Result<(), HostError> {
+        let _span = info_span!("load_vk", bytes = vk.len()).entered();
+        // The guest decodes the vk against the params it has by then
+        self.load_pending_params()?;
+        let buf = self.write_bytes(vk)?;
+        self.refill_gas();
+        let load_vk = self
+            .instance
+            .exports
+            .get_typed_function::<(u32, u32), u32>(&self.store, "load_vk")?;
+        let result = load_vk.call(&mut self.store, buf.ptr, buf.len);
+        let status = self.check_trap(result)?;
+        self.free(buf)?;
+
+        if status != abi::STATUS_VALID as u32 {
+            return Err(HostError::Abi(
+                "guest rejected the verifying key".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn free(&mut self, buf: GuestBuf) -> Result<(), HostError> {
+        self.refill_gas();
+        let dealloc = self
+            .instance
+            .exports
+            .get_typed_function::<(u32, u32), ()>(&self.store, "dealloc")?;
+        dealloc.call(&mut self.store, buf.ptr, buf.len)?;
+        Ok(())
+    }
+
+    fn trace_call(&mut self, started: Instant) {
+        let gas_used = self
+            .remaining_gas()
+            .zip(self.config.gas_limit)
+            .map(|(remaining, limit)| limit - remaining);
+        debug!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            gas_used, "guest call returned"
+        );
+    }
+
+    fn refill_gas(&mut self) {
+        if let Some(limit) = self.config.gas_limit {
+            set_remaining_points(&mut self.store, &self.instance, limit);
+        }
+    }
+
+    fn check_trap<T>(&mut self, result: Result<T, wasmer::RuntimeError>) -> Result<T, HostError> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(_) if self.out_of_gas() => Err(HostError::OutOfGas),
+            Err(_) if self.at_memory_limit() => Err(HostError::MemoryLimit {
+                limit: self.config.max_memory_pages.unwrap_or_default(),
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Gas left after the last call, `None` when metering is disabled
+    pub fn remaining_gas(&mut self) -> Option<u64> {
+        self.config.gas_limit?;
+        match get_remaining_points(&mut self.store, &self.instance) {
+            MeteringPoints::Remaining(points) => Some(points),
+            MeteringPoints::Exhausted => Some(0),
+        }
+    }
+
+    fn out_of_gas(&mut self) -> bool {
+        self.config.gas_limit.is_some()
+            && matches!(
+                get_remaining_points(&mut self.store, &self.instance),
+                MeteringPoints::Exhausted
+            )
+    }
+
+    // A failed memory.grow surfaces as an ordinary trap in the guest (usually an allocation
+    // failure), so blame the limit when the guest memory has reached it
+    fn at_memory_limit(&self) -> bool {
+        let Some(limit) = self.config.max_memory_pages else {
+            return false;
+        };
+        match self.instance.exports.get_memory("memory") {
+            Ok(memory) => memory.view(&self.store).size().0 >= limit,
+            Err(_) => false,
+        }
+    }
+}
+
+fn build_engine(config: &HostConfig) -> Engine {
+    let mut engine: Engine = match config.compiler {
+        Compiler::Singlepass => {
+            let mut compiler_config = Singlepass::new();
+            add_metering(&mut compiler_config, config);
+            compiler_config.into()
+        }
+        #[cfg(feature = "cranelift")]
+        Compiler::Cranelift => {
+            let mut compiler_config = Cranelift::new();
+            add_metering(&mut compiler_config, config);
+            compiler_config.into()
+        }
+    };
+
+    let tunables = LimitingTunables::new(
+        BaseTunables::for_target(&Target::default()),
+        config.max_memory_pages.map(Pages),
+        config.max_table_elements,
+    );
+    engine.set_tunables(tunables);
+    engine
+}
+
+fn add_metering(compiler_config: &mut impl CompilerConfig, config: &HostConfig) {
+    let Some(limit) = config.gas_limit else {
+        return;
+    };
+    let metering = match config.cost_function.clone() {
+        Some(cost_function) => Arc::new(Metering::new(limit, move |op: &Operator| -> u64 {
+            cost_function.cost(op)
+        })),
+        // Every operator costs one unit, so a hanging guest traps after `limit` operators
+        None => Arc::new(Metering::new(limit, |_: &Operator| -> u64 { 1 })),
+    };
+    compiler_config.push_middleware(metering);
+}
+
+fn check_declared_limits(module: &Module, config: &HostConfig) -> Result<(), HostError> {
+    for export in module.exports() {
+        match export.ty() {
+            ExternType::Memory(ty) => {
+                if let Some(limit) = config.max_memory_pages {
+                    if ty.minimum.0 > limit {
+                        return Err(HostError::MemoryLimit { limit });
+                    }
+                }
+            }
+            ExternType::Table(ty) => {
+                if let Some(limit) = config.max_table_elements {
+                    if ty.minimum > limit {
+                        return Err(HostError::TableLimit {
+                            requested: ty.minimum,
+                            limit,
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! Host side of the guest's phase markers (perf.rs). Every instance gets the
+//! `env.wv_perf_mark` import, which records the time and gas left at each call. Guests
+//! built without `perf` never call it.
+
+use std::time::{Duration, Instant};
+
+use wasmer::{AsStoreMut, Function, FunctionEnv, FunctionEnvMut, Imports};
+use wasmer_middlewares::metering::{get_remaining_points, MeteringPoints};
+
+use super::{DarkfiEnv, Harness, HostError};
+use crate::abi::Verdict;
+
+// Must match perf::MARK_*
+const MARK_START: u32 = 0;
+const MARK_TRANSCRIPT: u32 = 1;
+const MARK_OPENING: u32 = 2;
+const MARK_MSM: u32 = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PerfMark {
+    pub phase: u32,
+    pub at: Instant,
+    /// `None` when metering is disabled
+    pub remaining_gas: Option<u64>,
+}
+
+/// Cost of one phase of verification inside the guest
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PhaseCost {
+    pub elapsed: Duration,
+    /// Gas spent, roughly the number of wasm operators executed
+    pub gas: Option<u64>,
+}
+
+/// The guest-side counterpart of `VerifyReport`
+#[derive(Clone, Debug, Default)]
+pub struct GuestPerfReport {
+    /// Transcript parsing and the constraint check
+    pub transcript: PhaseCost,
+    /// The multiopen argument, up to the final MSM
+    pub opening: PhaseCost,
+    /// Evaluating the final MSM
+    pub msm: PhaseCost,
+}
+
+impl GuestPerfReport {
+    /// None unless `marks` hold one full verification
+    fn from_marks(marks: &[PerfMark]) -> Option<Self> {
+        let find = |phase| marks.iter().find(|mark| mark.phase == phase);
+        let cost = |from: &PerfMark, to: &PerfMark| PhaseCost {
+            elapsed: to.at.duration_since(from.at),
+            gas: from
+                .remaining_gas
+                .zip(to.remaining_gas)
+                .map(|(from, to)| from - to),
+        };
+        let start = find(MARK_START)?;
+        let transcript = find(MARK_TRANSCRIPT)?;
+        let opening = find(MARK_OPENING)?;
+        let msm = find(MARK_MSM)?;
+        Some(GuestPerfReport {
+            transcript: cost(start, transcript),
+            opening: cost(transcript, opening),
+            msm: cost(opening, msm),
+        })
+    }
+}
+
+impl core::fmt::Display for GuestPerfReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (name, cost) in [
+            ("transcript", self.transcript),
+            ("opening", self.opening),
+            ("msm", self.msm),
+        ] {
+            write!(f, "{} [{}us", name, cost.elapsed.as_micros())?;
+            if let Some(gas) = cost.gas {
+                write!(f, ", {} gas", gas)?;
+            }
+            write!(f, "] ")?;
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn define_imports(
+    imports: &mut Imports,
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<DarkfiEnv>,
+) {
+    imports.define(
+        "env",
+        "wv_perf_mark",
+        Function::new_typed_with_env(store, env, wv_perf_mark),
+    );
+}
+
+fn wv_perf_mark(mut env: FunctionEnvMut<DarkfiEnv>, phase: u32) {
+    let at = Instant::now();
+    // Only set for metered instances, reading the points of any other would panic
+    let remaining_gas = env.data().metered_instance.clone().map(|instance| {
+        match get_remaining_points(&mut env, &instance) {
+            MeteringPoints::Remaining(points) => points,
+            MeteringPoints::Exhausted => 0,
+        }
+    });
+    env.data_mut().perf_marks.push(PerfMark {
+        phase,
+        at,
+        remaining_gas,
+    });
+}
+
+impl Harness {
+    /// `verify`, also reporting what each phase cost inside the guest. The report is None
+    /// if the module wasn't built with the `perf` feature.
+    pub fn verify_with_perf(
+        &mut self,
+        proof: &[u8],
+        instances: &[u8],
+    ) -> Result<(Verdict, Option<GuestPerfReport>), HostError> {
+        self.darkfi_env_mut().perf_marks.clear();
+        let verdict = self.verify(proof, instances)?;
+        let report = GuestPerfReport::from_marks(&self.darkfi_env().perf_marks);
+        Ok((verdict, report))
+    }
+}
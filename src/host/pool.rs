@@ -0,0 +1,163 @@
+use std::sync::{Condvar, Mutex};
+
+use super::{CompiledModule, Harness, HostConfig, HostError, Snapshot};
+use crate::abi::Verdict;
+
+/// A fixed set of pre-instantiated guests shared between threads. The module is compiled
+/// once; each job borrows an idle instance and hands it back when the call returns.
+///
+/// An instance whose job failed or panicked may be left with corrupt memory or globals, so
+/// it is dropped instead of handed back, and a fresh one is instantiated in its place the
+/// next time an instance is needed.
+pub struct VerifierPool {
+    slots: Mutex<Slots>,
+    available: Condvar,
+    compiled: CompiledModule,
+    /// What replacement instances start from, see `new_warmed`
+    snapshot: Option<Snapshot>,
+    size: usize,
+}
+
+struct Slots {
+    idle: Vec<Harness>,
+    /// Instances dropped after a failure and not replaced yet
+    missing: usize,
+}
+
+/// A checked-out instance, returned to the pool on drop. Dropped without being marked
+/// healthy, e.g. on a panic, it is discarded.
+struct Checkout<'a> {
+    pool: &'a VerifierPool,
+    harness: Option<Harness>,
+    healthy: bool,
+}
+
+impl Drop for Checkout<'_> {
+    fn drop(&mut self) {
+        let mut slots = self.pool.slots.lock().unwrap_or_else(|e| e.into_inner());
+        match (self.healthy, self.harness.take()) {
+            (true, Some(harness)) => slots.idle.push(harness),
+            _ => slots.missing += 1,
+        }
+        self.pool.available.notify_one();
+    }
+}
+
+fn check_size(size: usize) -> Result<(), HostError> {
+    match size {
+        0 => Err(HostError::Config(
+            "a verifier pool needs at least one instance".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+impl VerifierPool {
+    pub fn new(wasm_bytes: &[u8], size: usize, config: HostConfig) -> Result<Self, HostError> {
+        Self::from_compiled(&CompiledModule::new(wasm_bytes, config)?, size)
+    }
+
+    pub fn from_compiled(compiled: &CompiledModule, size: usize) -> Result<Self, HostError> {
+        check_size(size)?;
+
+        let idle = (0..size)
+            .map(|_| compiled.instantiate())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(VerifierPool {
+            slots: Mutex::new(Slots { idle, missing: 0 }),
+            available: Condvar::new(),
+            compiled: compiled.clone(),
+            snapshot: None,
+            size,
+        })
+    }
+
+    /// Call `warmup` (e.g. `build_vk`) on one instance, then start every other instance
+    /// from a snapshot of it so the warm-up cost is paid once
+    pub fn new_warmed(
+        wasm_bytes: &[u8],
+        size: usize,
+        config: HostConfig,
+        warmup: &str,
+    ) -> Result<Self, HostError> {
+        check_size(size)?;
+
+        let compiled = CompiledModule::new(wasm_bytes, config)?;
+        let mut first = compiled.instantiate()?;
+        first.call(warmup)?;
+        let snapshot = first.snapshot()?;
+
+        let mut idle = vec![first];
+        for _ in 1..size {
+            idle.push(compiled.instantiate_from(&snapshot)?);
+        }
+
+        Ok(VerifierPool {
+            slots: Mutex::new(Slots { idle, missing: 0 }),
+            available: Condvar::new(),
+            compiled,
+            snapshot: Some(snapshot),
+            size,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Verify through the guest ABI on the next idle instance
+    pub fn verify(&self, proof: &[u8], instances: &[u8]) -> Result<Verdict, HostError> {
+        self.with_instance(|harness| harness.verify(proof, instances))
+    }
+
+    /// Call a no-argument export on the next idle instance, blocking until one is free
+    pub fn call(&self, name: &str) -> Result<(), HostError> {
+        self.with_instance(|harness| harness.call(name))
+    }
+
+    /// Run `job` against an idle instance, blocking until one is free. The instance is
+    /// replaced if `job` fails or panics.
+    pub fn with_instance<T>(
+        &self,
+        job: impl FnOnce(&mut Harness) -> Result<T, HostError>,
+    ) -> Result<T, HostError> {
+        let mut checkout = self.checkout()?;
+        let result = job(checkout.harness.as_mut().unwrap());
+        checkout.healthy = result.is_ok();
+        result
+    }
+
+    fn checkout(&self) -> Result<Checkout<'_>, HostError> {
+        let mut slots = self.slots.lock().unwrap();
+        loop {
+            if let Some(harness) = slots.idle.pop() {
+                return Ok(Checkout {
+                    pool: self,
+                    harness: Some(harness),
+                    healthy: false,
+                });
+            }
+            if slots.missing > 0 {
+                slots.missing -= 1;
+                drop(slots);
+                // A failed replacement counts as missing again through the guard's drop
+                let mut checkout = Checkout {
+                    pool: self,
+                    harness: None,
+                    healthy: false,
+                };
+                checkout.harness = Some(self.replacement()?);
+                return Ok(checkout);
+            }
+            slots = self.available.wait(slots).unwrap();
+        }
+    }
+
+    fn replacement(&self) -> Result<Harness, HostError> {
+        match &self.snapshot {
+            Some(snapshot) => self.compiled.instantiate_from(snapshot),
+            None => self.compiled.instantiate(),
+        }
+    }
+}
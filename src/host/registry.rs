@@ -0,0 +1,93 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use tracing::trace;
+
+use super::{
+    cache::{CacheKey, VerificationCache},
+    CompiledModule, HostConfig, HostError, VerifierPool,
+};
+use crate::abi::{Verdict, CIRCUIT_ID_SECTION};
+
+/// Verifier modules for several circuits, keyed by the circuit id each module carries in
+/// its custom section
+#[derive(Default)]
+pub struct ModuleRegistry {
+    pools: HashMap<String, VerifierPool>,
+    cache: Option<Mutex<VerificationCache>>,
+}
+
+impl ModuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the verdicts of the last `capacity` requests
+    pub fn with_cache(capacity: usize) -> Self {
+        ModuleRegistry {
+            pools: HashMap::new(),
+            cache: Some(Mutex::new(VerificationCache::new(capacity))),
+        }
+    }
+
+    /// Compile `wasm_bytes`, start `pool_size` instances of it and return its circuit id.
+    /// A module with an already registered circuit id replaces the old one.
+    pub fn register(
+        &mut self,
+        wasm_bytes: &[u8],
+        pool_size: usize,
+        config: HostConfig,
+    ) -> Result<String, HostError> {
+        let compiled = CompiledModule::new(wasm_bytes, config)?;
+        let circuit_id = compiled.circuit_id().ok_or_else(|| {
+            HostError::Abi(format!("module has no {} section", CIRCUIT_ID_SECTION))
+        })?;
+        let pool = VerifierPool::from_compiled(&compiled, pool_size)?;
+        self.pools.insert(circuit_id.clone(), pool);
+        // Verdicts of the replaced module no longer apply
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().clear();
+        }
+        Ok(circuit_id)
+    }
+
+    pub fn get(&self, circuit_id: &str) -> Option<&VerifierPool> {
+        self.pools.get(circuit_id)
+    }
+
+    pub fn circuit_ids(&self) -> impl Iterator<Item = &str> {
+        self.pools.keys().map(|id| id.as_str())
+    }
+
+    /// Route a verification request to the module registered for `circuit_id`, answering
+    /// from the cache when the same request was seen before
+    pub fn verify(
+        &self,
+        circuit_id: &str,
+        proof: &[u8],
+        instances: &[u8],
+    ) -> Result<Verdict, HostError> {
+        let pool = self
+            .get(circuit_id)
+            .ok_or_else(|| HostError::UnknownCircuit(circuit_id.to_string()))?;
+
+        let Some(cache) = &self.cache else {
+            return pool.verify(proof, instances);
+        };
+        let key = CacheKey::new(circuit_id, proof, instances);
+        if let Some(verdict) = cache.lock().unwrap().get(&key) {
+            trace!(
+                monotonic_counter.verification_cache_hits = 1_u64,
+                circuit_id
+            );
+            return Ok(verdict);
+        }
+        trace!(
+            monotonic_counter.verification_cache_misses = 1_u64,
+            circuit_id
+        );
+        // Don't hold the lock while in wasm, other circuits may be verifying meanwhile
+        let verdict = pool.verify(proof, instances)?;
+        cache.lock().unwrap().insert(key, verdict.clone());
+        Ok(verdict)
+    }
+}
@@ -0,0 +1,74 @@
+use super::{GuestBuf, Harness, HostError};
+use crate::abi::{self, Verdict};
+
+/// What the host needs from a wasm engine to drive the guest ABI. Implemented by the
+/// wasmer `Harness` and, with the `wasmi` feature, by the interpreter-based `WasmiHarness`.
+pub trait WasmRuntime {
+    /// Call a no-argument export
+    fn call(&mut self, name: &str) -> Result<(), HostError>;
+
+    /// Copy `bytes` into a fresh guest allocation
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<GuestBuf, HostError>;
+
+    /// Copy a guest buffer back out, leaving it allocated
+    fn read_bytes(&self, buf: GuestBuf) -> Result<Vec<u8>, HostError>;
+
+    fn free(&mut self, buf: GuestBuf) -> Result<(), HostError>;
+
+    /// Call the `verify` export and return the packed result pointer
+    fn call_verify(&mut self, proof: GuestBuf, instances: GuestBuf) -> Result<u64, HostError>;
+
+    /// Decode and free the result buffer returned by `verify`
+    fn read_result(&mut self, packed: u64) -> Result<Verdict, HostError> {
+        let (ptr, len) = abi::unpack(packed);
+        let buf = GuestBuf { ptr, len };
+        let bytes = self.read_bytes(buf)?;
+        self.free(buf)?;
+        Verdict::decode(&bytes).ok_or_else(|| HostError::Abi("malformed result buffer".to_string()))
+    }
+
+    /// Verify `proof` against `instances` (see `encode_instances`) through the guest's memory ABI
+    fn verify(&mut self, proof: &[u8], instances: &[u8]) -> Result<Verdict, HostError> {
+        verify_through_abi(self, proof, instances)
+    }
+}
+
+pub(super) fn verify_through_abi<R: WasmRuntime + ?Sized>(
+    runtime: &mut R,
+    proof: &[u8],
+    instances: &[u8],
+) -> Result<Verdict, HostError> {
+    let proof_buf = runtime.write_bytes(proof)?;
+    let instances_buf = runtime.write_bytes(instances)?;
+    let packed = runtime.call_verify(proof_buf, instances_buf)?;
+
+    runtime.free(proof_buf)?;
+    runtime.free(instances_buf)?;
+    runtime.read_result(packed)
+}
+
+impl WasmRuntime for Harness {
+    fn call(&mut self, name: &str) -> Result<(), HostError> {
+        Harness::call(self, name)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<GuestBuf, HostError> {
+        Harness::write_bytes(self, bytes)
+    }
+
+    fn read_bytes(&self, buf: GuestBuf) -> Result<Vec<u8>, HostError> {
+        Harness::read_bytes(self, buf)
+    }
+
+    fn free(&mut self, buf: GuestBuf) -> Result<(), HostError> {
+        Harness::free(self, buf)
+    }
+
+    fn call_verify(&mut self, proof: GuestBuf, instances: GuestBuf) -> Result<u64, HostError> {
+        Harness::call_verify(self, proof, instances)
+    }
+
+    fn verify(&mut self, proof: &[u8], instances: &[u8]) -> Result<Verdict, HostError> {
+        Harness::verify(self, proof, instances)
+    }
+}
@@ -0,0 +1,111 @@
+use std::ptr::NonNull;
+
+use wasmer::{
+    vm::{self, MemoryError, MemoryStyle, TableStyle, VMMemoryDefinition, VMTableDefinition},
+    MemoryType, Pages, TableType, Tunables,
+};
+
+/// Wraps the engine's tunables and refuses memories or tables above the configured caps.
+/// Adapted from wasmer's `tunables_limit_memory` example.
+pub struct LimitingTunables<T: Tunables> {
+    max_memory: Option<Pages>,
+    max_table_elements: Option<u32>,
+    base: T,
+}
+
+impl<T: Tunables> LimitingTunables<T> {
+    pub fn new(base: T, max_memory: Option<Pages>, max_table_elements: Option<u32>) -> Self {
+        Self {
+            max_memory,
+            max_table_elements,
+            base,
+        }
+    }
+
+    // Rust guests don't declare a memory maximum, so cap it here to make memory.grow fail
+    fn adjust_memory(&self, requested: &MemoryType) -> MemoryType {
+        let mut adjusted = *requested;
+        if let Some(limit) = self.max_memory {
+            adjusted.maximum = Some(requested.maximum.map_or(limit, |max| max.min(limit)));
+        }
+        adjusted
+    }
+
+    fn validate_memory(&self, ty: &MemoryType) -> Result<(), MemoryError> {
+        match self.max_memory {
+            Some(limit) if ty.minimum > limit => Err(MemoryError::Generic(format!(
+                "minimum of {} pages exceeds the limit of {} pages",
+                ty.minimum.0, limit.0
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn adjust_table(&self, requested: &TableType) -> TableType {
+        let mut adjusted = *requested;
+        if let Some(limit) = self.max_table_elements {
+            adjusted.maximum = Some(requested.maximum.map_or(limit, |max| max.min(limit)));
+        }
+        adjusted
+    }
+
+    fn validate_table(&self, ty: &TableType) -> Result<(), String> {
+        match self.max_table_elements {
+            Some(limit) if ty.minimum > limit => Err(format!(
+                "minimum of {} elements exceeds the limit of {} elements",
+                ty.minimum, limit
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T: Tunables> Tunables for LimitingTunables<T> {
+    fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+        self.base.memory_style(&self.adjust_memory(memory))
+    }
+
+    fn table_style(&self, table: &TableType) -> TableStyle {
+        self.base.table_style(&self.adjust_table(table))
+    }
+
+    fn create_host_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+    ) -> Result<vm::VMMemory, MemoryError> {
+        let adjusted = self.adjust_memory(ty);
+        self.validate_memory(&adjusted)?;
+        self.base.create_host_memory(&adjusted, style)
+    }
+
+    unsafe fn create_vm_memory(
+        &self,
+        ty: &MemoryType,
+        style: &MemoryStyle,
+        vm_definition_location: NonNull<VMMemoryDefinition>,
+    ) -> Result<vm::VMMemory, MemoryError> {
+        let adjusted = self.adjust_memory(ty);
+        self.validate_memory(&adjusted)?;
+        self.base
+            .create_vm_memory(&adjusted, style, vm_definition_location)
+    }
+
+    fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<vm::VMTable, String> {
+        let adjusted = self.adjust_table(ty);
+        self.validate_table(&adjusted)?;
+        self.base.create_host_table(&adjusted, style)
+    }
+
+    unsafe fn create_vm_table(
+        &self,
+        ty: &TableType,
+        style: &TableStyle,
+        vm_definition_location: NonNull<VMTableDefinition>,
+    ) -> Result<vm::VMTable, String> {
+        let adjusted = self.adjust_table(ty);
+        self.validate_table(&adjusted)?;
+        self.base
+            .create_vm_table(&adjusted, style, vm_definition_location)
+    }
+}
@@ -0,0 +1,134 @@
+//! Interpreter backend for hosts that can't JIT (nested wasm, embedded). Slower than
+//! wasmer but speaks the same guest ABI. The darkfi imports are not provided here.
+
+use wasmi::{Config, Engine, Instance, Linker, Memory, Module, Store};
+
+use super::{GuestBuf, HostConfig, HostError, WasmRuntime};
+
+fn interp_error(e: impl core::fmt::Display) -> HostError {
+    HostError::Interpreter(e.to_string())
+}
+
+pub struct WasmiHarness {
+    store: Store<()>,
+    instance: Instance,
+    memory: Memory,
+    gas_limit: Option<u64>,
+    // Total fuel handed to the store so far, wasmi only lets us add fuel
+    fuel_added: u64,
+}
+
+impl WasmiHarness {
+    /// Only `gas_limit` is honoured from `config`, as wasmi fuel with one unit per instruction
+    pub fn new(wasm_bytes: &[u8], config: &HostConfig) -> Result<Self, HostError> {
+        let mut engine_config = Config::default();
+        engine_config.consume_fuel(config.gas_limit.is_some());
+        let engine = Engine::new(&engine_config);
+
+        let module = Module::new(&engine, wasm_bytes).map_err(interp_error)?;
+        let mut store = Store::new(&engine, ());
+        let linker = <Linker<()>>::new(&engine);
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(interp_error)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| HostError::Abi("module exports no memory".to_string()))?;
+
+        Ok(WasmiHarness {
+            store,
+            instance,
+            memory,
+            gas_limit: config.gas_limit,
+            fuel_added: 0,
+        })
+    }
+
+    pub fn memory_pages(&self) -> u32 {
+        u32::from(self.memory.current_pages(&self.store))
+    }
+
+    fn remaining_fuel(&self) -> u64 {
+        self.fuel_added - self.store.fuel_consumed().unwrap_or(0)
+    }
+
+    fn refill_gas(&mut self) -> Result<(), HostError> {
+        if let Some(limit) = self.gas_limit {
+            let missing = limit.saturating_sub(self.remaining_fuel());
+            self.store.add_fuel(missing).map_err(interp_error)?;
+            self.fuel_added += missing;
+        }
+        Ok(())
+    }
+
+    fn check_trap<T>(&self, result: Result<T, wasmi::Error>) -> Result<T, HostError> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(_) if self.gas_limit.is_some() && self.remaining_fuel() == 0 => {
+                Err(HostError::OutOfGas)
+            }
+            Err(e) => Err(interp_error(e)),
+        }
+    }
+}
+
+impl WasmRuntime for WasmiHarness {
+    fn call(&mut self, name: &str) -> Result<(), HostError> {
+        self.refill_gas()?;
+        let function = self
+            .instance
+            .get_typed_func::<(), ()>(&self.store, name)
+            .map_err(interp_error)?;
+        let result = function.call(&mut self.store, ());
+        self.check_trap(result.map_err(wasmi::Error::from))
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<GuestBuf, HostError> {
+        let len = u32::try_from(bytes.len())
+            .map_err(|_| HostError::Abi("buffer does not fit in guest memory".to_string()))?;
+        // Fuel starts at zero and a previous call may have used it all up
+        self.refill_gas()?;
+        let alloc = self
+            .instance
+            .get_typed_func::<u32, u32>(&self.store, "alloc")
+            .map_err(interp_error)?;
+        let result = alloc.call(&mut self.store, len);
+        let ptr = self.check_trap(result.map_err(wasmi::Error::from))?;
+        self.memory
+            .write(&mut self.store, ptr as usize, bytes)
+            .map_err(interp_error)?;
+        Ok(GuestBuf { ptr, len })
+    }
+
+    fn read_bytes(&self, buf: GuestBuf) -> Result<Vec<u8>, HostError> {
+        let mut bytes = vec![0u8; buf.len as usize];
+        self.memory
+            .read(&self.store, buf.ptr as usize, &mut bytes)
+            .map_err(interp_error)?;
+        Ok(bytes)
+    }
+
+    fn free(&mut self, buf: GuestBuf) -> Result<(), HostError> {
+        self.refill_gas()?;
+        let dealloc = self
+            .instance
+            .get_typed_func::<(u32, u32), ()>(&self.store, "dealloc")
+            .map_err(interp_error)?;
+        let result = dealloc.call(&mut self.store, (buf.ptr, buf.len));
+        self.check_trap(result.map_err(wasmi::Error::from))
+    }
+
+    fn call_verify(&mut self, proof: GuestBuf, instances: GuestBuf) -> Result<u64, HostError> {
+        self.refill_gas()?;
+        let verify = self
+            .instance
+            .get_typed_func::<(u32, u32, u32, u32), u64>(&self.store, "verify")
+            .map_err(interp_error)?;
+        let result = verify.call(
+            &mut self.store,
+            (proof.ptr, proof.len, instances.ptr, instances.len),
+        );
+        self.check_trap(result.map_err(wasmi::Error::from))
+    }
+}
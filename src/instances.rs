@@ -0,0 +1,113 @@
+//! The one byte encoding of public inputs, shared by the guest ABI, the host harness and
+//! serde: the count as u32 LE followed by each element's 32-byte little-endian repr.
+
+use halo2_proofs::pasta::{group::ff::PrimeField, pallas};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstanceError {
+    /// Fewer bytes than the length prefix announces, or none at all
+    Truncated,
+    /// Bytes left over after the announced instances
+    TrailingBytes,
+    /// The element at this index is not below the field modulus
+    NonCanonical(usize),
+}
+
+impl core::fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InstanceError::Truncated => write!(f, "instance encoding is truncated"),
+            InstanceError::TrailingBytes => write!(f, "instance encoding has trailing bytes"),
+            InstanceError::NonCanonical(i) => {
+                write!(f, "instance {} is not a canonical field element", i)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InstanceError {}
+
+pub fn encode_instances(instances: &[pallas::Base]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(4 + 32 * instances.len());
+    bytes.extend_from_slice(&(instances.len() as u32).to_le_bytes());
+    for instance in instances {
+        bytes.extend_from_slice(&instance.to_repr());
+    }
+    bytes
+}
+
+pub fn decode_instances(bytes: &[u8]) -> Result<Vec<pallas::Base>, InstanceError> {
+    let (len, body) = bytes
+        .split_first_chunk::<4>()
+        .ok_or(InstanceError::Truncated)?;
+    let len = u32::from_le_bytes(*len) as usize;
+    let expected = len.checked_mul(32).ok_or(InstanceError::Truncated)?;
+    if body.len() < expected {
+        return Err(InstanceError::Truncated);
+    }
+    if body.len() > expected {
+        return Err(InstanceError::TrailingBytes);
+    }
+
+    body.chunks_exact(32)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let repr: [u8; 32] = chunk.try_into().unwrap();
+            Option::from(pallas::Base::from_repr(repr)).ok_or(InstanceError::NonCanonical(i))
+        })
+        .collect()
+}
+
+/// blake2b-256 of the encoded instances, compared in constant time
+#[derive(Clone, Copy, Debug)]
+pub struct InstanceDigest(pub [u8; 32]);
+
+impl InstanceDigest {
+    pub fn of(instances: &[pallas::Base]) -> Self {
+        let hash = blake2b_simd::Params::new()
+            .hash_length(32)
+            .personal(b"WasmVerifierInst")
+            .hash(&encode_instances(instances));
+        InstanceDigest(hash.as_bytes().try_into().unwrap())
+    }
+}
+
+impl PartialEq for InstanceDigest {
+    fn eq(&self, other: &Self) -> bool {
+        crate::ct::ct_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for InstanceDigest {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_encoding() {
+        let instances = vec![pallas::Base::from(111), -pallas::Base::from(1)];
+        let bytes = encode_instances(&instances);
+        assert_eq!(bytes.len(), 4 + 64);
+        assert_eq!(decode_instances(&bytes).unwrap(), instances);
+
+        assert_eq!(
+            decode_instances(&bytes[..40]),
+            Err(InstanceError::Truncated)
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            decode_instances(&trailing),
+            Err(InstanceError::TrailingBytes)
+        );
+
+        // The modulus minus one plus one wraps to a non-canonical repr
+        let mut non_canonical = bytes;
+        non_canonical[36] += 1;
+        assert_eq!(
+            decode_instances(&non_canonical),
+            Err(InstanceError::NonCanonical(1))
+        );
+    }
+}
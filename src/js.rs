@@ -0,0 +1,134 @@
+//! JS/TS bindings through wasm-bindgen, behind the `js` feature. `make npm` packs them
+//! with wasm-pack, TypeScript declarations included.
+//!
+//! Byte buffers are `Uint8Array`s, instances in the `encodeInstances` encoding. A proof
+//! that doesn't verify makes `verify` return false; malformed input and failures throw a
+//! `VerifyError` whose `kind` says which. The exports are camelCase and so don't collide
+//! with the guest ABI's `verify` and `alloc`.
+
+use halo2_proofs::pasta::{group::ff::PrimeField, pallas};
+use wasm_bindgen::prelude::*;
+
+use crate::{abi::Verdict, verify_bytes, MyCircuit};
+
+const K: u32 = 4;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The inputs could not be decoded
+    Malformed,
+    /// Verification could not run, e.g. keygen failed
+    Error,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct VerifyError {
+    kind: ErrorKind,
+    message: String,
+}
+
+#[wasm_bindgen]
+impl VerifyError {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+}
+
+fn malformed(e: impl core::fmt::Display) -> VerifyError {
+    VerifyError {
+        kind: ErrorKind::Malformed,
+        message: e.to_string(),
+    }
+}
+
+#[wasm_bindgen(js_name = VerifyingKey)]
+pub struct JsVerifyingKey(crate::VerifyingKey);
+
+#[wasm_bindgen(js_class = VerifyingKey)]
+impl JsVerifyingKey {
+    /// The arithmetic circuit's key, from keygen in the browser
+    pub fn arith() -> Result<JsVerifyingKey, VerifyError> {
+        crate::VerifyingKey::build(K, &MyCircuit::default())
+            .map(|vk| {
+                JsVerifyingKey(vk.with_instance_lengths(MyCircuit::INSTANCE_LENGTHS.to_vec()))
+            })
+            .map_err(|e| VerifyError {
+                kind: ErrorKind::Error,
+                message: e.to_string(),
+            })
+    }
+
+    /// A key serialized by `toBytes` or `VerifyingKey::to_bytes`
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<JsVerifyingKey, VerifyError> {
+        crate::VerifyingKey::from_bytes(bytes, MyCircuit::default())
+            .map(|vk| {
+                JsVerifyingKey(vk.with_instance_lengths(MyCircuit::INSTANCE_LENGTHS.to_vec()))
+            })
+            .map_err(malformed)
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes()
+    }
+
+    pub fn digest(&self) -> Vec<u8> {
+        self.0.digest().to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn k(&self) -> u32 {
+        self.0.k
+    }
+
+    pub fn verify(&self, proof: &[u8], instances: &[u8]) -> Result<bool, VerifyError> {
+        match Verdict::of(verify_bytes(&self.0, proof, instances)) {
+            Verdict::Valid => Ok(true),
+            Verdict::Invalid(_) => Ok(false),
+            Verdict::Malformed(message) => Err(VerifyError {
+                kind: ErrorKind::Malformed,
+                message,
+            }),
+            Verdict::Error(message) => Err(VerifyError {
+                kind: ErrorKind::Error,
+                message,
+            }),
+        }
+    }
+}
+
+/// Field elements as concatenated 32-byte little-endian reprs, into the instance encoding
+#[wasm_bindgen(js_name = encodeInstances)]
+pub fn encode_instances(elements: &[u8]) -> Result<Vec<u8>, VerifyError> {
+    if elements.len() % 32 != 0 {
+        return Err(malformed("field elements are 32 bytes each"));
+    }
+    let instances = elements
+        .chunks_exact(32)
+        .map(|chunk| {
+            let repr: [u8; 32] = chunk.try_into().unwrap();
+            Option::from(pallas::Base::from_repr(repr))
+                .ok_or_else(|| malformed("not a canonical field element"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(crate::encode_instances(&instances))
+}
+
+/// The reverse of `encodeInstances`
+#[wasm_bindgen(js_name = decodeInstances)]
+pub fn decode_instances(bytes: &[u8]) -> Result<Vec<u8>, VerifyError> {
+    let instances = crate::decode_instances(bytes).map_err(malformed)?;
+    Ok(instances
+        .iter()
+        .flat_map(|instance| instance.to_repr())
+        .collect())
+}
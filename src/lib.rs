@@ -22,96 +22,135 @@
 
 */
 
+pub mod abi;
+pub mod accumulator;
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "batch")]
+pub mod batch;
+pub mod bounded;
+#[cfg(feature = "bulk")]
+pub mod bulk;
+pub mod cancel;
+mod circuit;
+#[cfg(all(feature = "cosmwasm", target_arch = "wasm32"))]
+pub mod cosmwasm;
+pub mod ct;
+#[cfg(all(feature = "darkfi", target_arch = "wasm32"))]
+pub mod darkfi;
+pub mod dry_run;
+pub mod envelope;
+pub mod error;
+#[cfg(all(feature = "ffi", not(target_arch = "wasm32")))]
+pub mod ffi;
 pub mod gadget;
+pub mod hex;
+pub mod instances;
+#[cfg(all(feature = "js", target_arch = "wasm32"))]
+pub mod js;
+#[cfg(feature = "prover")]
+pub mod memory;
+pub mod params;
+#[cfg(feature = "perf")]
+pub mod perf;
+#[cfg(feature = "prover")]
+pub mod progress;
+#[cfg(feature = "prover")]
+pub mod prover;
+#[cfg(all(feature = "python", not(target_arch = "wasm32")))]
+mod python;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod report;
+pub mod rows;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(all(feature = "solana", target_os = "solana"))]
+mod solana;
+#[cfg(feature = "srs")]
+pub mod srs;
+pub mod statement;
+mod structure;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod threads;
+mod transcript;
+pub mod vk_cache;
+mod vk_json;
+pub mod vk_registry;
+pub mod witness;
 
-use crate::gadget::{
-    arithmetic::{ArithChip, ArithConfig, ArithInstruction},
-    assign_free_advice,
+#[cfg(feature = "wasm_verify")]
+pub mod host;
+
+// I: Batch inversion on the verification path. Already the case inside halo2: the
+// Lagrange evaluations at x (`l_i_range`) and the multiopen interpolation denominators
+// are each inverted as one batch, leaving single inversions only for `x^n - 1` and the
+// point decompressions, neither of which this crate reaches.
+
+#[cfg(all(target_arch = "wasm32", feature = "multicore"))]
+compile_error!("the `multicore` feature is native only, the wasm guest has no threads");
+#[cfg(all(target_os = "solana", feature = "solana", feature = "fixtures"))]
+compile_error!("build the Solana program with --no-default-features, see solana.rs");
+
+pub use crate::circuit::expected_instances;
+pub use crate::error::{Error, Result};
+pub use crate::instances::{decode_instances, encode_instances, InstanceDigest, InstanceError};
+pub use crate::statement::Statement;
+pub use crate::structure::ProofShape;
+pub use crate::vk_registry::VkRegistry;
+
+use crate::circuit::MyCircuit;
+
+use std::{
+    io,
+    sync::{Arc, OnceLock},
 };
+
+#[cfg(any(test, feature = "fixtures"))]
+use halo2_proofs::circuit::Value;
 use halo2_proofs::{
-    circuit::*,
     pasta::{pallas, vesta},
     plonk,
     plonk::*,
     poly::commitment::Params,
-    transcript::Blake2bRead,
 };
 
+// I: A `no_std` verify for Substrate runtimes. Not possible from this crate: halo2_proofs
+// 0.3 is std-only, its vk and proof reading goes through `std::io::Read` and its
+// `Error::Transcript` wraps `std::io::Error`, with no feature to turn either off. A
+// runtime can still call `verify_bytes` through a `#[runtime_interface]` host function,
+// i.e. natively on the node, which is the usual route for std-only crypto.
+
 ////////////////////////////////////////////////// Circuit ///////////////////////////////////////////////
 
 // Q: The most important question is: what is the proof size? what is the verification time ie the virtualization penalty?
 
-#[derive(Clone)]
-struct MyConfig {
-    instance: Column<Instance>,
-    advices: [Column<Advice>; 3],
-    arith_config: ArithConfig,
-}
+// See circuit.rs
 
-#[derive(Default, Clone)]
-struct MyCircuit {
-    a: Value<pallas::Base>,
-    b: Value<pallas::Base>,
-}
+// I: Recursion, verifying a vesta-side proof inside a pallas circuit over the pasta cycle, so
+// the wasm verifier only checks one aggregate proof. Not attempted: halo2_gadgets has no
+// in-circuit verifier for IPA proofs (transcript hashing, the multiopen argument and the
+// deferred MSM over the other curve all need gadgets of their own). accumulator.rs is the
+// out-of-circuit half of that design.
 
-// By using a trait bound with an impl block that uses generic type parameters,
-// we can implement methods conditionally for types that implement the specified traits.
-// https://doc.rust-lang.org/book/ch10-02-traits.html
-impl Circuit<pallas::Base> for MyCircuit {
-    type Config = MyConfig;
-    type FloorPlanner = SimpleFloorPlanner;
-
-    fn without_witnesses(&self) -> Self {
-        Self::default()
-    }
-
-    fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
-        let advices = [
-            meta.advice_column(),
-            meta.advice_column(),
-            meta.advice_column(),
-        ];
-        let instance = meta.instance_column();
-
-        meta.enable_equality(instance);
-        meta.enable_equality(advices[0]);
-        meta.enable_equality(advices[1]);
-        meta.enable_equality(advices[2]);
-
-        Self::Config {
-            instance,
-            advices,
-            arith_config: ArithChip::configure(meta, advices[0], advices[1], advices[2]),
-        }
-    }
+// I: Solidity verifiers and calldata for the same circuit, to compare wasm against EVM
+// verification cost. Not possible on this backend: snark-verifier targets halo2 over
+// bn256 with KZG, where the final check is one pairing the EVM has a precompile for.
+// Here it's IPA over pasta, whose final check is an MSM of size 2^k on a curve the EVM
+// has no precompiles for, so there is no verifier contract worth emitting until a KZG
+// backend exists.
 
-    fn synthesize(
-        &self,
-        config: Self::Config,
-        mut layouter: impl Layouter<pallas::Base>,
-    ) -> Result<(), Error> {
-        let arith_chip = ArithChip::construct(config.arith_config);
-        let a = assign_free_advice(layouter.namespace(|| "load a"), config.advices[0], self.a)?;
-        let b = assign_free_advice(layouter.namespace(|| "load b"), config.advices[1], self.b)?;
-
-        let sum = arith_chip.add(layouter.namespace(|| "a + b"), &a, &b)?;
-        layouter.constrain_instance(sum.cell(), config.instance, 0)?;
-
-        // Q: Why is the selector cell of the mul region in a different column that those of sum and sub gate?
-        let product = arith_chip.mul(layouter.namespace(|| "a * b"), &a, &b)?;
-        layouter.constrain_instance(product.cell(), config.instance, 1)?;
+////////////////////////////////////////////////// Proof ///////////////////////////////////////////////
 
-        let diff = arith_chip.sub(layouter.namespace(|| "a - b"), &a, &b)?;
-        layouter.constrain_instance(diff.cell(), config.instance, 2)?;
+#[derive(Clone, Default)]
+pub struct Proof(Vec<u8>);
 
-        Ok(())
+impl PartialEq for Proof {
+    fn eq(&self, other: &Self) -> bool {
+        ct::ct_eq(&self.0, &other.0)
     }
 }
 
-////////////////////////////////////////////////// Proof ///////////////////////////////////////////////
-
-#[derive(Clone, Default, PartialEq, Eq)]
-pub struct Proof(Vec<u8>);
+impl Eq for Proof {}
 
 impl AsRef<[u8]> for Proof {
     fn as_ref(&self) -> &[u8] {
@@ -120,21 +159,69 @@ impl AsRef<[u8]> for Proof {
 }
 
 impl Proof {
+    /// `instances` holds one slice per instance column of the circuit
     pub fn verify(
         &self,
         vk: &VerifyingKey,
-        instances: &[pallas::Base],
+        instances: &[&[pallas::Base]],
+    ) -> std::result::Result<(), plonk::Error> {
+        Self::verify_reader(vk, &self.0[..], instances)
+    }
+
+    /// `verify` for a circuit with one instance column, with the instances streamed from
+    /// an iterator, e.g. a decoder. halo2 takes them as a slice, so they are gathered into
+    /// one buffer sized from the vk's instance lengths, but the caller builds none.
+    pub fn verify_iter(
+        &self,
+        vk: &VerifyingKey,
+        instances: impl IntoIterator<Item = pallas::Base>,
+    ) -> Result<()> {
+        let instances = instances.into_iter();
+        let capacity = match &vk.instance_lengths {
+            Some(lengths) => lengths.iter().sum(),
+            None => instances.size_hint().0,
+        };
+        let mut column = Vec::with_capacity(capacity);
+        column.extend(instances);
+        vk.check_instances(&[&column])?;
+        self.verify(vk, &[&column])?;
+        Ok(())
+    }
+
+    /// Verify a proof read straight from `reader`, e.g. a borrowed buffer or a file, without
+    /// first collecting it into a `Proof`
+    pub fn verify_reader<R: io::Read>(
+        vk: &VerifyingKey,
+        mut reader: R,
+        instances: &[&[pallas::Base]],
     ) -> std::result::Result<(), plonk::Error> {
+        #[cfg(not(feature = "perf"))]
         let strategy = SingleVerifier::new(&vk.params);
-        let mut transcript = Blake2bRead::init(&self.0[..]);
-
-        plonk::verify_proof(
-            &vk.params,
-            &vk.vk,
-            strategy,
-            &[&[instances]],
-            &mut transcript,
-        )
+        #[cfg(feature = "perf")]
+        let strategy = {
+            perf::mark(perf::MARK_START);
+            perf::MarkingVerifier::new(&vk.params)
+        };
+        let mut transcript = transcript::reader(&mut reader);
+
+        plonk::verify_proof(&vk.params, &vk.vk, strategy, &[instances], &mut transcript)?;
+        ensure_consumed(reader)
+    }
+
+    /// Verify a proof of several circuits at once, from `Proof::create_multi`. `instances`
+    /// holds each circuit's instance columns in the order they were proved. Such proofs
+    /// are longer than `VerifyingKey::proof_size`, so skip `validate_structure` for them.
+    pub fn verify_multi(
+        &self,
+        vk: &VerifyingKey,
+        instances: &[&[&[pallas::Base]]],
+    ) -> std::result::Result<(), plonk::Error> {
+        let strategy = SingleVerifier::new(&vk.params);
+        let mut reader = &self.0[..];
+        let mut transcript = transcript::reader(&mut reader);
+
+        plonk::verify_proof(&vk.params, &vk.vk, strategy, instances, &mut transcript)?;
+        ensure_consumed(reader)
     }
 
     pub fn new(bytes: Vec<u8>) -> Self {
@@ -142,31 +229,376 @@ impl Proof {
     }
 }
 
+// I: Memoizing verifier strategy state across calls. Nothing in `Proof::verify_reader` is
+// worth it: `SingleVerifier::new` only wraps `params.empty_msm()`, which allocates nothing
+// until the proof's terms are added, and the vk's transcript hash is computed once at
+// keygen. The per-call work derived from params, committing the instance columns over
+// `g_lagrange` and the final MSM over `g`, depends on the instances and the proof.
+// Fixed-base tables for `g` would help that MSM but need halo2's private MSM internals,
+// see report.rs.
+
+/// The transcript must end where verification stopped reading. Otherwise the same proof
+/// could be submitted under many encodings, which breaks anything keyed by proof bytes.
+pub(crate) fn ensure_consumed<R: io::Read>(mut reader: R) -> std::result::Result<(), plonk::Error> {
+    match reader.read(&mut [0u8; 1])? {
+        0 => Ok(()),
+        _ => Err(plonk::Error::Transcript(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "trailing bytes after the transcript",
+        ))),
+    }
+}
+
+/// Verify a proof against instances in the `encode_instances` encoding, so callers never
+/// handle field elements. Fails with `Error::Instance` if the instances don't decode.
+///
+/// `proof` is a bare transcript or a `ProofEnvelope` in either encoding, told apart by
+/// `ProofFormat::detect`. An envelope's header is checked against `vk` too.
+pub fn verify_bytes(vk: &VerifyingKey, proof: &[u8], instances: &[u8]) -> Result<()> {
+    let envelope = match envelope::ProofFormat::detect(proof) {
+        envelope::ProofFormat::Legacy => None,
+        _ => Some(envelope::migrate(proof, vk, 0)?),
+    };
+    let transcript = envelope
+        .as_ref()
+        .map_or(proof, |envelope| envelope.proof.as_ref());
+    Proof::validate_len(transcript.len(), vk)?;
+    let instances = decode_instances(instances)?;
+    vk.check_instances(&[&instances])?;
+    match &envelope {
+        Some(envelope) => envelope.verify(vk, &[&instances])?,
+        None => Proof::verify_reader(vk, proof, &[&instances])?,
+    }
+    Ok(())
+}
+
+/// Verify many proofs against one vk, e.g. a mempool's worth, and return one result per
+/// item. Spread over the current rayon pool on native builds (see `threads`), sequential
+/// in wasm.
+pub fn verify_many(
+    vk: &VerifyingKey,
+    items: &[(&Proof, &[&[pallas::Base]])],
+) -> Vec<std::result::Result<(), plonk::Error>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use rayon::prelude::*;
+        items
+            .par_iter()
+            .map(|(proof, instances)| proof.verify(vk, instances))
+            .collect()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        items
+            .iter()
+            .map(|(proof, instances)| proof.verify(vk, instances))
+            .collect()
+    }
+}
+
+/// Verify proofs for any mix of circuits, e.g. all the proofs in a transaction, and return
+/// one result per item. Every item is verified even after a failure.
+pub fn verify_all(
+    items: &[(&VerifyingKey, &Proof, &[&[pallas::Base]])],
+) -> Vec<std::result::Result<(), plonk::Error>> {
+    items
+        .iter()
+        .map(|(vk, proof, instances)| proof.verify(vk, instances))
+        .collect()
+}
+
+impl Proof {
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+
+    pub fn from_hex(s: &str) -> std::result::Result<Self, hex::HexError> {
+        hex::decode(s).map(Proof)
+    }
+
+    /// blake2b-256 of the proof bytes
+    pub fn digest(&self) -> [u8; 32] {
+        let hash = blake2b_simd::Params::new().hash_length(32).hash(&self.0);
+        hash.as_bytes().try_into().unwrap()
+    }
+}
+
+impl core::str::FromStr for Proof {
+    type Err = hex::HexError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Proof::from_hex(s)
+    }
+}
+
+// Length and a digest prefix, the full bytes are useless in logs (use `to_hex` for those)
+impl core::fmt::Display for Proof {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Proof({} bytes, {})",
+            self.0.len(),
+            hex::encode(&self.digest()[..8])
+        )
+    }
+}
+
 impl core::fmt::Debug for Proof {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "Proof({:?})", self.0)
+        core::fmt::Display::fmt(self, f)
+    }
+}
+
+/// A proof borrowed from wherever its bytes already are, e.g. `include_bytes!` or guest
+/// memory, so verifying it needs no copy
+#[derive(Clone, Copy)]
+pub struct ProofRef<'a>(&'a [u8]);
+
+impl<'a> ProofRef<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        ProofRef(bytes)
+    }
+
+    /// As `Proof::verify`
+    pub fn verify(
+        &self,
+        vk: &VerifyingKey,
+        instances: &[&[pallas::Base]],
+    ) -> std::result::Result<(), plonk::Error> {
+        Proof::verify_reader(vk, self.0, instances)
+    }
+
+    pub fn to_proof(&self) -> Proof {
+        Proof(self.0.to_vec())
+    }
+}
+
+impl AsRef<[u8]> for ProofRef<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+impl PartialEq for ProofRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        ct::ct_eq(self.0, other.0)
+    }
+}
+
+impl Eq for ProofRef<'_> {}
+
+impl<'a> From<&'a Proof> for ProofRef<'a> {
+    fn from(proof: &'a Proof) -> Self {
+        ProofRef(&proof.0)
+    }
+}
+
+impl core::fmt::Debug for ProofRef<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "ProofRef({} bytes)", self.0.len())
+    }
+}
+
+/// The public inputs of a proof, mostly useful as a serializable unit
+#[derive(Clone, Debug, Default)]
+pub struct Instances(pub Vec<pallas::Base>);
+
+impl Instances {
+    pub fn digest(&self) -> InstanceDigest {
+        InstanceDigest::of(&self.0)
+    }
+}
+
+impl PartialEq for Instances {
+    fn eq(&self, other: &Self) -> bool {
+        ct::ct_eq_instances(&self.0, &other.0)
+    }
+}
+
+impl Eq for Instances {}
+
+impl AsRef<[pallas::Base]> for Instances {
+    fn as_ref(&self) -> &[pallas::Base] {
+        &self.0
     }
 }
 
 /////////////////////////////////////////// VerifyingKey //////////////////////////////////////////////
 
+const VK_MAGIC: &[u8; 4] = b"WVVK";
+const VK_VERSION: u8 = 1;
+
 #[derive(Clone, Debug)]
 pub struct VerifyingKey {
-    pub params: Params<vesta::Affine>,
+    pub k: u32,
+    /// Shared with every other key of the same k, see `params::shared`
+    pub params: Arc<Params<vesta::Affine>>,
     pub vk: plonk::VerifyingKey<vesta::Affine>,
+    /// Transcript length of a proof for this key, see `proof_size`
+    proof_size: OnceLock<usize>,
+    /// Hash of the serialized key, see `digest`
+    digest: OnceLock<[u8; 32]>,
+    /// Instances per column, when known for the circuit, see `check_instances`
+    instance_lengths: Option<Vec<usize>>,
 }
 
 impl VerifyingKey {
-    pub fn build(k: u32, c: &impl Circuit<pallas::Base>) -> Self {
-        let params = Params::new(k);
-        let vk = plonk::keygen_vk(&params, c).unwrap();
-        VerifyingKey { params, vk }
+    pub fn new(
+        k: u32,
+        params: Arc<Params<vesta::Affine>>,
+        vk: plonk::VerifyingKey<vesta::Affine>,
+    ) -> Self {
+        VerifyingKey {
+            k,
+            params,
+            vk,
+            proof_size: OnceLock::new(),
+            digest: OnceLock::new(),
+            instance_lengths: None,
+        }
+    }
+
+    /// Record how many instances each column of the circuit takes. halo2's vk doesn't say,
+    /// so without this `check_instances` only checks what it does record.
+    pub fn with_instance_lengths(mut self, lengths: Vec<usize>) -> Self {
+        self.instance_lengths = Some(lengths);
+        self
+    }
+
+    pub fn build(k: u32, c: &impl Circuit<pallas::Base>) -> Result<Self> {
+        Self::build_with_params(k, params::shared(k), c)
+    }
+
+    /// Like `build`, but with params of size `k` obtained elsewhere (e.g. injected by the host).
+    /// `build` itself shares one set of params per k across the process, see `params::shared`.
+    pub fn build_with_params(
+        k: u32,
+        params: Arc<Params<vesta::Affine>>,
+        c: &impl Circuit<pallas::Base>,
+    ) -> Result<Self> {
+        let vk = plonk::keygen_vk(&params, c)?;
+        Ok(VerifyingKey::new(k, params, vk))
+    }
+
+    /// Serialized as magic, version, k, params, vk
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(VK_MAGIC)?;
+        writer.write_all(&[VK_VERSION])?;
+        writer.write_all(&self.k.to_le_bytes())?;
+        self.params.write(writer)?;
+        self.vk.write(writer)
+    }
+
+    /// Read a key written by `write`. The circuit is needed to rebuild the constraint system.
+    pub fn read<R: io::Read, C: Circuit<pallas::Base>>(
+        reader: &mut R,
+        circuit: C,
+    ) -> io::Result<Self> {
+        let mut header = [0u8; 9];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != VK_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a verifying key",
+            ));
+        }
+        if header[4] != VK_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported verifying key version {}", header[4]),
+            ));
+        }
+        let k = u32::from_le_bytes(header[5..9].try_into().unwrap());
+
+        let params = Arc::new(Params::read(&mut *reader)?);
+        let vk = plonk::VerifyingKey::read::<R, C>(reader, &params, circuit)?;
+        // check_instances bounds the usable rows by k, it has to be the key's own
+        if k != crate::params::k_of(&params) || k != vk.get_domain().k() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "header says k = {}, the key is for k = {}",
+                    k,
+                    vk.get_domain().k()
+                ),
+            ));
+        }
+        Ok(VerifyingKey::new(k, params, vk))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        // Writing to a Vec can't fail
+        self.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Stable blake2b-256 of the serialized key (k, params and vk). This is the circuit
+    /// identifier used by proof envelopes.
+    pub fn digest(&self) -> [u8; 32] {
+        *self.digest.get_or_init(|| {
+            let hash = blake2b_simd::Params::new()
+                .hash_length(32)
+                .personal(b"WasmVerifierVK__")
+                .hash(&self.to_bytes());
+            hash.as_bytes().try_into().unwrap()
+        })
+    }
+
+    pub fn from_bytes<C: Circuit<pallas::Base>>(mut bytes: &[u8], circuit: C) -> io::Result<Self> {
+        Self::read(&mut bytes, circuit)
+    }
+
+    /// Read a key in halo2's bare encoding, as written by `plonk::VerifyingKey::write` in
+    /// other tooling with the same pasta/IPA setup. That encoding carries no params, so they
+    /// must be supplied and k is taken from them.
+    pub fn read_halo2<R: io::Read, C: Circuit<pallas::Base>>(
+        reader: &mut R,
+        params: Arc<Params<vesta::Affine>>,
+        circuit: C,
+    ) -> io::Result<Self> {
+        let vk = plonk::VerifyingKey::read::<R, C>(reader, &params, circuit)?;
+        let k = vk.get_domain().k();
+        Ok(VerifyingKey::new(k, params, vk))
+    }
+
+    /// The vk generated by build.rs, so only deserialization is left to do at runtime
+    #[cfg(feature = "embedded_keys")]
+    pub(crate) fn embedded() -> io::Result<Self> {
+        let bytes = include_bytes!(concat!(env!("OUT_DIR"), "/vk.bin"));
+        Self::from_bytes(bytes, MyCircuit::default())
+    }
+
+    pub fn from_halo2_bytes<C: Circuit<pallas::Base>>(
+        mut bytes: &[u8],
+        params: Arc<Params<vesta::Affine>>,
+        circuit: C,
+    ) -> io::Result<Self> {
+        Self::read_halo2(&mut bytes, params, circuit)
     }
 }
 
+// I: Precomputed bases. `embedded_keys` already runs all of keygen in build.rs, and the
+// params it embeds carry `g_lagrange` alongside `g`, so the guest does no MSMs or FFTs in
+// `VerifyingKey::embedded`. What's left is parsing: halo2 0.3 only reads compressed points,
+// which costs a square root per point of the params and the vk. Skipping that needs an
+// uncompressed reader for `Params` and `plonk::VerifyingKey`, whose fields are private to
+// halo2.
+
+// I: A u64-limb arithmetic path for wasm. Not possible from this crate: pasta_curves already
+// stores Fp/Fq as four u64 limbs, and its Montgomery multiplication widens each limb
+// product to u128, which wasm32 lowers to a `__multi3` call per product. Avoiding that
+// means 32-bit limbs (or a 52-bit float trick) inside pasta_curves itself, which has no
+// backend switch, so it would be a fork patched in with `[patch]`. `make bench` is the
+// baseline to hold such a fork against.
+
 /////////////////////////////////////////// Wasm entrypoint //////////////////////////////////////////////
 
+// The entrypoints below verify the `proof.bin` fixture and only exist with the default
+// `fixtures` feature. Verifier modules built for real use leave it out, see `make wasm-min`.
+
 // I: Optimization idea: AOT compilation and caching the native code
+#[cfg(feature = "fixtures")]
 #[no_mangle]
 pub extern "C" fn entrypoint() {
     let k = 4;
@@ -174,20 +606,26 @@ pub extern "C" fn entrypoint() {
         a: Value::known(pallas::Base::from(69)),
         b: Value::known(pallas::Base::from(42)),
     };
-    let public_inputs = vec![
-        pallas::Base::from(69 + 42),
-        pallas::Base::from(69 * 42),
-        pallas::Base::from(69 - 42),
-    ];
-    let vk = VerifyingKey::build(k, &circuit);
+    let public_inputs = expected_instances(pallas::Base::from(69), pallas::Base::from(42));
+    let vk = VerifyingKey::build(k, &circuit).unwrap();
+
+    let proof = ProofRef::new(include_bytes!("../proof.bin"));
+    assert!(proof.verify(&vk, &[&public_inputs]).is_ok());
+}
 
-    let proof_bytes = include_bytes!("../proof.bin");
-    let proof_vec = proof_bytes.to_vec();
-    let proof = Proof::new(proof_vec);
-    assert!(proof.verify(&vk, &public_inputs).is_ok());
+// Same verification, with the vk and params precomputed by build.rs
+#[cfg(all(feature = "embedded_keys", feature = "fixtures"))]
+#[no_mangle]
+pub extern "C" fn entrypoint_embedded() {
+    let public_inputs = expected_instances(pallas::Base::from(69), pallas::Base::from(42));
+    let vk = VerifyingKey::embedded().unwrap();
+
+    let proof = ProofRef::new(include_bytes!("../proof.bin"));
+    assert!(proof.verify(&vk, &[&public_inputs]).is_ok());
 }
 
 // Same work, but do not verify
+#[cfg(feature = "fixtures")]
 #[no_mangle]
 pub extern "C" fn entrypoint_no_verify() {
     let k = 4;
@@ -200,15 +638,14 @@ pub extern "C" fn entrypoint_no_verify() {
         pallas::Base::from(69 * 42),
         pallas::Base::from(60 - 42),
     ];
-    let _vk = VerifyingKey::build(k, &circuit);
+    let _vk = VerifyingKey::build(k, &circuit).unwrap();
 
     // include_bytes has no runtime cost: https://stackoverflow.com/a/61625729
-    let proof_bytes = include_bytes!("../proof.bin");
-    let proof_vec = proof_bytes.to_vec();
-    let _ = Proof::new(proof_vec);
-    // let _ = proof.verify(&vk, &public_inputs);
+    let _proof_bytes = include_bytes!("../proof.bin");
+    // let _ = Proof::verify_reader(&vk, &proof_bytes[..], &[&public_inputs]);
 }
 
+#[cfg(feature = "fixtures")]
 #[no_mangle]
 pub extern "C" fn entrypoint_no_verify_no_vk() {
     let _k = 4;
@@ -224,10 +661,8 @@ pub extern "C" fn entrypoint_no_verify_no_vk() {
     // let _vk = VerifyingKey::build(k, &circuit);
 
     // include_bytes has no runtime cost: https://stackoverflow.com/a/61625729
-    let proof_bytes = include_bytes!("../proof.bin");
-    let proof_vec = proof_bytes.to_vec();
-    let _ = Proof::new(proof_vec);
-    // let _ = proof.verify(&vk, &public_inputs);
+    let _proof_bytes = include_bytes!("../proof.bin");
+    // let _ = Proof::verify_reader(&vk, &proof_bytes[..], &[&public_inputs]);
 }
 
 //////////////////////////////////////////// Tests ///////////////////////////////////
@@ -235,46 +670,9 @@ pub extern "C" fn entrypoint_no_verify_no_vk() {
 #[cfg(all(test, feature = "gen_proof"))]
 mod tests {
     use super::*;
+    use crate::prover::{ArithWitness, Prover, ProvingKey};
     use halo2_proofs::dev::MockProver;
-    use halo2_proofs::transcript::Blake2bWrite;
     use rand::rngs::OsRng;
-    use rand::RngCore;
-
-    #[derive(Clone, Debug)]
-    pub struct ProvingKey {
-        pub params: Params<vesta::Affine>,
-        pub pk: plonk::ProvingKey<vesta::Affine>,
-    }
-
-    impl ProvingKey {
-        pub fn build(k: u32, c: &impl Circuit<pallas::Base>) -> Self {
-            let params = Params::new(k);
-            let vk = plonk::keygen_vk(&params, c).unwrap();
-            let pk = plonk::keygen_pk(&params, vk, c).unwrap();
-            ProvingKey { params, pk }
-        }
-    }
-
-    impl Proof {
-        pub fn create(
-            pk: ProvingKey,
-            circuits: &[impl Circuit<pallas::Base>],
-            instances: &[pallas::Base],
-            mut rng: impl RngCore,
-        ) -> std::result::Result<Self, plonk::Error> {
-            let mut transcript = Blake2bWrite::<_, vesta::Affine, _>::init(vec![]);
-            plonk::create_proof(
-                &pk.params,
-                &pk.pk,
-                circuits,
-                &[&[instances]],
-                &mut rng,
-                &mut transcript,
-            )?;
-
-            Ok(Proof(transcript.finalize()))
-        }
-    }
 
     #[test]
     fn test_circuit() {
@@ -294,82 +692,816 @@ mod tests {
 
         let k = 4;
 
-        let pk = ProvingKey::build(k, &circuit);
-        let public_inputs = vec![
-            pallas::Base::from(69 + 42),
-            pallas::Base::from(69 * 42),
-            pallas::Base::from(69 - 42),
-        ];
+        let pk = ProvingKey::build(k, &circuit).unwrap();
+        let public_inputs = expected_instances(pallas::Base::from(69), pallas::Base::from(42));
 
         // Alternative API
         // let prover = MockProver::run(k, &circuit, vec![public_inputs.clone()]).unwrap();
         // prover.assert_satisfied();
 
-        let proof = Proof::create(pk, &[circuit.clone()], &public_inputs, &mut OsRng).unwrap();
-        let vk = super::VerifyingKey::build(k, &circuit);
-        assert!(proof.verify(&vk, &public_inputs).is_ok());
+        let proof = Proof::create(&pk, &[circuit.clone()], &public_inputs, &mut OsRng).unwrap();
+        let vk = super::VerifyingKey::build(k, &circuit).unwrap();
+        assert!(proof.verify(&vk, &[&public_inputs]).is_ok());
 
         println!("Proof size [{} kB]", proof.as_ref().len() as f64 / 1024.0);
+        println!(
+            "{}",
+            proof.verify_with_report(&vk, &[&public_inputs]).unwrap()
+        );
 
         let mut file = std::fs::File::create("proof.bin").unwrap();
         use std::io::{Read, Write};
         file.write_all(proof.as_ref());
     }
+
+    /// The arith circuit's vk, with the `proof.bin` fixture and the instances it proves
+    fn fixture() -> (super::VerifyingKey, Proof, Vec<pallas::Base>) {
+        let vk = super::VerifyingKey::build(4, &MyCircuit::default()).unwrap();
+        let proof = Proof::new(include_bytes!("../proof.bin").to_vec());
+        let public_inputs = expected_instances(pallas::Base::from(69), pallas::Base::from(42));
+        (vk, proof, public_inputs)
+    }
+
+    /// A prover for the arith circuit, with a witness and the instances it proves
+    fn prover_fixture() -> (Prover, ArithWitness, Vec<pallas::Base>) {
+        let prover = Prover::build(4).unwrap();
+        let witness = ArithWitness {
+            a: pallas::Base::from(69),
+            b: pallas::Base::from(42),
+        };
+        let public_inputs = witness.expected_instances();
+        (prover, witness, public_inputs)
+    }
+
+    #[test]
+    fn test_prover() {
+        let (prover, witness, public_inputs) = prover_fixture();
+        let proof = prover.prove(&witness, &public_inputs, OsRng).unwrap();
+        assert!(proof
+            .verify(&prover.verifying_key(), &[&public_inputs])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_proof_ref() {
+        let (vk, proof, public_inputs) = fixture();
+        let borrowed = ProofRef::new(proof.as_ref());
+        assert!(borrowed.verify(&vk, &[&public_inputs]).is_ok());
+        assert!(borrowed.to_proof() == proof);
+    }
+
+    #[test]
+    fn test_prove_deterministic() {
+        let (prover, witness, public_inputs) = prover_fixture();
+        let seeded = prover
+            .prove_deterministic(&witness, &public_inputs, [7; 32])
+            .unwrap();
+        assert!(seeded
+            .verify(&prover.verifying_key(), &[&public_inputs])
+            .is_ok());
+        let again = prover
+            .prove_deterministic(&witness, &public_inputs, [7; 32])
+            .unwrap();
+        assert_eq!(seeded.as_ref(), again.as_ref());
+    }
+
+    #[test]
+    fn test_prove_into() {
+        let (prover, witness, public_inputs) = prover_fixture();
+        let mut buffer = b"header".to_vec();
+        prover
+            .prove_into(&witness, &public_inputs, OsRng, &mut buffer)
+            .unwrap();
+        assert_eq!(&buffer[..6], b"header");
+        assert!(Proof::new(buffer[6..].to_vec())
+            .verify(&prover.verifying_key(), &[&public_inputs])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_prove_with_progress() {
+        use crate::progress::ProvePhase::*;
+
+        let (prover, witness, public_inputs) = prover_fixture();
+        let mut phases = vec![];
+        let proof = prover
+            .prove_with_progress(&witness, &public_inputs, OsRng, |phase| phases.push(phase))
+            .unwrap();
+        assert!(proof
+            .verify(&prover.verifying_key(), &[&public_inputs])
+            .is_ok());
+        assert_eq!(phases, [Synthesis, Commitments, Evaluations, Opening, Done]);
+    }
+
+    #[test]
+    fn test_prove_many() {
+        let (prover, witness, public_inputs) = prover_fixture();
+        let other = ArithWitness {
+            a: pallas::Base::from(5),
+            b: pallas::Base::from(3),
+        };
+        let other_inputs = other.expected_instances();
+        let proofs = prover
+            .prove_many(
+                &[(&witness, &public_inputs[..]), (&other, &other_inputs[..])],
+                OsRng,
+            )
+            .unwrap();
+        assert_eq!(proofs.len(), 2);
+        assert!(proofs[0]
+            .verify(&prover.verifying_key(), &[&public_inputs])
+            .is_ok());
+        assert!(proofs[1]
+            .verify(&prover.verifying_key(), &[&other_inputs])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_prove_multi() {
+        let (prover, witness, public_inputs) = prover_fixture();
+        let other = ArithWitness {
+            a: pallas::Base::from(5),
+            b: pallas::Base::from(3),
+        };
+        let other_inputs = other.expected_instances();
+        let items = [(&witness, &public_inputs[..]), (&other, &other_inputs[..])];
+        let proof = prover.prove_multi(&items, OsRng).unwrap();
+        assert!(proof
+            .verify_multi(
+                &prover.verifying_key(),
+                &[&[&public_inputs[..]], &[&other_inputs[..]]]
+            )
+            .is_ok());
+        assert!(proof
+            .verify_multi(
+                &prover.verifying_key(),
+                &[&[&other_inputs[..]], &[&public_inputs[..]]]
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let (prover, witness, public_inputs) = prover_fixture();
+        assert!(prover.dry_run(&witness, &public_inputs).is_ok());
+
+        let wrong_product = vec![public_inputs[0], pallas::Base::from(1), public_inputs[2]];
+        let failures = prover.dry_run(&witness, &wrong_product).unwrap_err();
+        assert!(!failures.0.is_empty());
+
+        // Too small for the circuit's rows
+        let failures =
+            crate::dry_run::dry_run(1, &MyCircuit::from(&witness), &[&public_inputs]).unwrap_err();
+        assert!(!failures.0.is_empty());
+    }
+
+    #[test]
+    fn test_proof_envelope() {
+        use crate::envelope::{EnvelopeError, ProofEnvelope};
+
+        let (vk, proof, public_inputs) = fixture();
+
+        let envelope = ProofEnvelope::new(&vk, proof, public_inputs.len());
+        let envelope = ProofEnvelope::from_bytes(&envelope.to_bytes()).unwrap();
+        assert!(envelope.verify(&vk, &[&public_inputs]).is_ok());
+        assert!(matches!(
+            envelope.verify(&vk, &[&public_inputs[..2]]),
+            Err(EnvelopeError::InstanceCount { .. })
+        ));
+    }
+
+    #[test]
+    fn test_compact_proof_envelope() {
+        use crate::envelope::{EnvelopeError, ProofEnvelope};
+
+        let (vk, proof, public_inputs) = fixture();
+
+        let envelope = ProofEnvelope::new(&vk, proof, public_inputs.len());
+        let compact = envelope.to_compact_bytes();
+        assert!(compact.len() < envelope.to_bytes().len());
+        assert_eq!(
+            ProofEnvelope::from_compact_bytes(&compact).unwrap(),
+            envelope
+        );
+        assert!(verify_bytes(&vk, &compact, &encode_instances(&public_inputs)).is_ok());
+        assert!(matches!(
+            verify_bytes(&vk, &compact, &encode_instances(&public_inputs[..2])),
+            Err(Error::Envelope(EnvelopeError::InstanceCount { .. }))
+        ));
+
+        for len in 0..compact.len() {
+            assert!(ProofEnvelope::from_compact_bytes(&compact[..len]).is_err());
+        }
+        let mut trailing = compact.clone();
+        trailing.push(0);
+        assert!(ProofEnvelope::from_compact_bytes(&trailing).is_err());
+
+        // A header full of continuation bytes never ends its varints
+        let mut garbage = compact[..37].to_vec();
+        garbage.extend_from_slice(&[0xff; 64]);
+        assert!(matches!(
+            verify_bytes(&vk, &garbage, &encode_instances(&public_inputs)),
+            Err(Error::Envelope(EnvelopeError::Malformed(_)))
+        ));
+        let mut wrong_circuit = compact;
+        wrong_circuit[5] ^= 1;
+        assert!(matches!(
+            verify_bytes(&vk, &wrong_circuit, &encode_instances(&public_inputs)),
+            Err(Error::Envelope(EnvelopeError::CircuitMismatch))
+        ));
+    }
+
+    #[test]
+    fn test_statement() {
+        use crate::statement::StatementError;
+
+        let (vk, proof, public_inputs) = fixture();
+        let mut registry = VkRegistry::new();
+        let statement = Statement {
+            circuit_id: registry.insert(vk),
+            instances: public_inputs,
+            proof,
+        };
+
+        let decoded = Statement::deserialize(&statement.serialize()).unwrap();
+        assert_eq!(decoded, statement);
+        assert!(decoded.verify(&registry).is_ok());
+        assert!(matches!(
+            decoded.verify(&VkRegistry::new()),
+            Err(StatementError::UnknownCircuit(_))
+        ));
+    }
+
+    #[cfg(feature = "batch")]
+    #[test]
+    fn test_batch_verify() {
+        use crate::batch::BatchVerifier;
+
+        let (vk, proof, public_inputs) = fixture();
+
+        let mut batch = BatchVerifier::new(&vk);
+        batch.add(&proof, &[&public_inputs]);
+        batch.add(&proof, &[&public_inputs]);
+        assert!(batch.finalize());
+
+        let mut batch = BatchVerifier::new(&vk);
+        batch.add(&proof, &[&public_inputs]);
+        batch.add(&proof, &[&public_inputs[..2]]);
+        assert_eq!(batch.finalize_or_find_invalid(), Err(vec![1]));
+    }
+
+    #[cfg(feature = "bulk")]
+    #[test]
+    fn test_bulk_verify() {
+        use crate::{abi::Verdict, bulk};
+
+        let vk = bulk::arith_vk(None).unwrap();
+        let proof = hex::encode(include_bytes!("../proof.bin"));
+        let valid = hex::encode(&encode_instances(&expected_instances(
+            pallas::Base::from(69),
+            pallas::Base::from(42),
+        )));
+        let wrong = hex::encode(&encode_instances(&expected_instances(
+            pallas::Base::from(69),
+            pallas::Base::from(43),
+        )));
+        let csv = format!(
+            "proof,instances\n{p},{valid}\n{p},{wrong}\n\n{p},00\n{p},{valid}\n",
+            p = proof
+        );
+
+        let rows = bulk::read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 4);
+        let verdicts = bulk::verify_rows(&vk, &rows, 2);
+        assert_eq!(verdicts[0], Verdict::Valid);
+        assert!(matches!(verdicts[1], Verdict::Invalid(_)));
+        assert!(matches!(verdicts[2], Verdict::Malformed(_)));
+        assert_eq!(verdicts[3], Verdict::Valid);
+
+        let mut out = vec![];
+        bulk::write_csv(&mut out, &verdicts).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().nth(1), Some("1,valid,"));
+        assert!(out.lines().nth(2).unwrap().starts_with("2,invalid,"));
+    }
+
+    #[test]
+    fn test_verify_all() {
+        let (vk, proof, public_inputs) = fixture();
+        let wrong_inputs = vec![pallas::Base::from(0); 3];
+
+        let results = verify_all(&[
+            (&vk, &proof, &[&public_inputs[..]][..]),
+            (&vk, &proof, &[&wrong_inputs[..]][..]),
+            (&vk, &proof, &[&public_inputs[..]][..]),
+        ]);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+
+        let results = verify_many(
+            &vk,
+            &[
+                (&proof, &[&public_inputs[..]][..]),
+                (&proof, &[&wrong_inputs[..]][..]),
+            ],
+        );
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+
+        let threads = crate::threads::Threads::new(2).unwrap();
+        assert_eq!(threads.threads(), 2);
+        let results = threads.verify_many(&vk, &[(&proof, &[&public_inputs[..]][..])]);
+        assert!(results[0].is_ok());
+    }
+
+    #[test]
+    fn test_trailing_bytes() {
+        let (vk, proof, public_inputs) = fixture();
+        let mut bytes = proof.as_ref().to_vec();
+        assert!(Proof::new(bytes.clone())
+            .verify(&vk, &[&public_inputs])
+            .is_ok());
+        assert!(verify_bytes(&vk, &bytes, &encode_instances(&public_inputs)).is_ok());
+        assert!(matches!(
+            verify_bytes(&vk, &bytes, &[1, 2, 3]),
+            Err(Error::Instance(_))
+        ));
+
+        assert_eq!(vk.proof_size(), bytes.len());
+        let shape = vk.shape();
+        assert_eq!((shape.advice_columns, shape.instance_columns), (3, 1));
+        assert_eq!(shape.proof_bytes, bytes.len());
+        assert!(Proof::new(bytes.clone()).validate_structure(&vk).is_ok());
+
+        bytes.push(0);
+        assert!(Proof::new(bytes.clone()).validate_structure(&vk).is_err());
+        assert!(Proof::new(bytes).verify(&vk, &[&public_inputs]).is_err());
+    }
+
+    #[test]
+    fn test_instance_precheck() {
+        let (vk, proof, public_inputs) = fixture();
+        let bytes = proof.as_ref();
+        assert!(vk.check_instances(&[&public_inputs[..2]]).is_ok());
+
+        let vk = vk.with_instance_lengths(vec![3]);
+        assert!(vk.check_instances(&[&public_inputs]).is_ok());
+        assert!(matches!(
+            verify_bytes(&vk, bytes, &encode_instances(&public_inputs[..2])),
+            Err(Error::InstanceCount {
+                column: 0,
+                expected: 3,
+                found: 2
+            })
+        ));
+        assert!(matches!(
+            vk.check_instances(&[]),
+            Err(Error::InstanceColumns {
+                expected: 1,
+                found: 0
+            })
+        ));
+        let mismatched = vk.clone().with_instance_lengths(vec![3, 1]);
+        assert!(matches!(
+            mismatched.check_instances(&[&public_inputs]),
+            Err(Error::InstanceColumns {
+                expected: 2,
+                found: 1
+            })
+        ));
+
+        assert!(proof
+            .verify_iter(&vk, public_inputs.iter().copied())
+            .is_ok());
+        assert!(proof
+            .verify_iter(&vk, public_inputs.iter().copied().take(2))
+            .is_err());
+    }
+
+    #[test]
+    fn test_accumulator() {
+        use crate::accumulator::Accumulator;
+
+        let (vk, proof, public_inputs) = fixture();
+        let wrong_inputs = vec![pallas::Base::from(0); 3];
+
+        let mut acc = Accumulator::new(&vk);
+        acc.add(&proof, &[&public_inputs]).unwrap();
+        acc.add(&proof, &[&public_inputs]).unwrap();
+        assert!(acc.finalize());
+
+        let mut acc = Accumulator::new(&vk);
+        acc.add(&proof, &[&public_inputs]).unwrap();
+        acc.add(&proof, &[&wrong_inputs]).unwrap();
+        assert!(!acc.finalize());
+    }
+
+    #[test]
+    fn test_cancellation() {
+        use crate::cancel::CancelToken;
+
+        let (vk, proof, public_inputs) = fixture();
+
+        let token = CancelToken::new();
+        assert!(proof
+            .verify_cancellable(&vk, &[&public_inputs], &token)
+            .is_ok());
+        token.clone().cancel();
+        assert!(matches!(
+            proof.verify_cancellable(&vk, &[&public_inputs], &token),
+            Err(Error::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn test_proof_migration() {
+        use crate::envelope::{migrate, ProofFormat};
+
+        let (vk, proof, public_inputs) = fixture();
+
+        // proof.bin predates envelopes
+        let legacy = proof.as_ref();
+        assert_eq!(ProofFormat::detect(legacy), ProofFormat::Legacy);
+        let upgraded = migrate(legacy, &vk, public_inputs.len()).unwrap();
+        assert!(upgraded.verify(&vk, &[&public_inputs]).is_ok());
+
+        let current = upgraded.to_bytes();
+        assert_eq!(ProofFormat::detect(&current), ProofFormat::Envelope(1));
+        assert_eq!(migrate(&current, &vk, 0).unwrap(), upgraded);
+    }
+
+    #[test]
+    fn test_vk_serialization() {
+        let (vk, _, _) = fixture();
+        let bytes = vk.to_bytes();
+        let read = super::VerifyingKey::from_bytes(&bytes, MyCircuit::default()).unwrap();
+        assert_eq!(read.k, 4);
+        assert_eq!(read.to_bytes(), bytes);
+
+        assert!(super::VerifyingKey::from_bytes(&bytes[1..], MyCircuit::default()).is_err());
+        let mut wrong_k = bytes.clone();
+        wrong_k[5..9].copy_from_slice(&5u32.to_le_bytes());
+        assert!(super::VerifyingKey::from_bytes(&wrong_k, MyCircuit::default()).is_err());
+
+        let mut halo2_bytes = vec![];
+        vk.vk.write(&mut halo2_bytes).unwrap();
+        let imported = super::VerifyingKey::from_halo2_bytes(
+            &halo2_bytes,
+            vk.params.clone(),
+            MyCircuit::default(),
+        )
+        .unwrap();
+        assert_eq!(imported.k, 4);
+        assert_eq!(imported.digest(), vk.digest());
+
+        let json = vk.to_json();
+        assert!(json.starts_with("{\"k\":4,"));
+        assert!(json.contains(&hex::encode(&vk.digest())));
+    }
+
+    /// The guest gets only halo2's verifier half: features are unified per target, so a
+    /// native-only feature leaking into the wasm32 graph would bloat every module. Runs
+    /// `cargo tree`, which needs the registry, see `make guest-deps`.
+    #[test]
+    #[ignore = "runs cargo tree, see `make guest-deps`"]
+    fn test_guest_dependencies() {
+        let output = std::process::Command::new(env!("CARGO"))
+            .args([
+                "tree",
+                "--target",
+                "wasm32-unknown-unknown",
+                "-e",
+                "normal,build,features",
+            ])
+            .args(["--prefix", "none", "--manifest-path"])
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let tree = String::from_utf8(output.stdout).unwrap();
+        // Whole package and feature lines, `maybe-rayon` is fine where `rayon` isn't
+        let has = |line: &str| tree.lines().any(|l| l.starts_with(line));
+
+        assert!(has("halo2_proofs v"));
+        for native_only in [
+            "halo2_proofs feature \"multicore\"",
+            "halo2_proofs feature \"batch\"",
+            "halo2_proofs feature \"dev-graph\"",
+            "rayon v",
+            "plotters v",
+            "rand_chacha v",
+            "wasmer v",
+        ] {
+            assert!(!has(native_only), "{} in the guest", native_only);
+        }
+    }
 }
 
 #[cfg(all(test, feature = "wasm_verify"))]
 mod tests {
-    use std::time::Instant;
-    use wasmer_compiler_singlepass::Singlepass;
-
     #[test]
     fn test_wasm_verify() {
-        use wasmer::FunctionEnv;
-        use wasmer::{imports, Instance, Module, Store};
+        use crate::host::bench::compare_engines;
 
-        let now = Instant::now();
-        /////////// Basically setting up the wasm runtime /////////
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        for entrypoint in [
+            "entrypoint",
+            "entrypoint_no_verify",
+            "entrypoint_no_verify_no_vk",
+        ] {
+            let report = compare_engines(wasm_bytes, entrypoint).unwrap();
+            print!("{}", report);
+        }
+    }
 
-        // IMPORTANT: Singlepass to match darkfi. Singlepass compiles under 500ms at the cost longer runtime
-        let compiler_config = Singlepass::new();
-        let mut store = Store::new(compiler_config);
+    #[test]
+    fn test_wasm_out_of_gas() {
+        use crate::host::{Harness, HostConfig, HostError};
 
-        // let mut store = Store::default();
-        let _env = FunctionEnv::new(&mut store, ());
         let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
-        let module = Module::new(&store, wasm_bytes).unwrap();
-        let import_object = imports! {};
-        let instance = Instance::new(&mut store, &module, &import_object).unwrap();
-        // Why does darkfi runtime compile wasm everytime, instead of compiling once?
-        let entrypoint = instance.exports.get_function("entrypoint").unwrap();
-        println!("wasm setup [{:?}ms]", now.elapsed().as_millis());
-
-        let now = Instant::now();
-        let _answer = entrypoint.call(&mut store, &[]).unwrap().to_vec();
-        println!(
-            "wasm built vk and verifed in [{:?}ms]",
-            now.elapsed().as_millis()
+        let config = HostConfig {
+            gas_limit: Some(1_000),
+            ..Default::default()
+        };
+        let mut harness = Harness::new(wasm_bytes, config).unwrap();
+        assert!(matches!(
+            harness.call("entrypoint"),
+            Err(HostError::OutOfGas)
+        ));
+    }
+
+    #[test]
+    fn test_wasm_write_after_out_of_gas() {
+        use crate::host::{Harness, HostConfig, HostError};
+
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let config = HostConfig {
+            gas_limit: Some(1_000),
+            ..Default::default()
+        };
+        let mut harness = Harness::new(wasm_bytes, config).unwrap();
+        assert!(matches!(
+            harness.call("entrypoint"),
+            Err(HostError::OutOfGas)
+        ));
+        let buf = harness.write_bytes(&[1, 2, 3]).unwrap();
+        assert_eq!(harness.read_bytes(buf).unwrap(), [1, 2, 3]);
+        harness.free(buf).unwrap();
+    }
+
+    #[cfg(feature = "wasmi")]
+    #[test]
+    fn test_wasmi_verify_abi() {
+        use crate::encode_instances;
+        use crate::host::{wasmi::WasmiHarness, HostConfig, HostError, WasmRuntime};
+        use halo2_proofs::pasta::pallas;
+
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let mut harness = WasmiHarness::new(wasm_bytes, &HostConfig::default()).unwrap();
+
+        let proof = include_bytes!("../proof.bin");
+        let public_inputs =
+            crate::expected_instances(pallas::Base::from(69), pallas::Base::from(42));
+        let instances = encode_instances(&public_inputs);
+        assert!(WasmRuntime::verify(&mut harness, proof, &instances)
+            .unwrap()
+            .is_valid());
+        let too_few = encode_instances(&public_inputs[1..]);
+        assert!(!WasmRuntime::verify(&mut harness, proof, &too_few)
+            .unwrap()
+            .is_valid());
+
+        // Running out of fuel leaves the harness usable
+        let config = HostConfig {
+            gas_limit: Some(1_000),
+            ..Default::default()
+        };
+        let mut harness = WasmiHarness::new(wasm_bytes, &config).unwrap();
+        assert!(matches!(
+            harness.call("entrypoint"),
+            Err(HostError::OutOfGas)
+        ));
+        let buf = harness.write_bytes(&[1, 2, 3]).unwrap();
+        harness.free(buf).unwrap();
+
+        // As in `call`, an allocation that runs out of fuel is out of gas, not a trap
+        let config = HostConfig {
+            gas_limit: Some(1),
+            ..Default::default()
+        };
+        let mut harness = WasmiHarness::new(wasm_bytes, &config).unwrap();
+        assert!(matches!(
+            harness.write_bytes(&[1, 2, 3]),
+            Err(HostError::OutOfGas)
+        ));
+    }
+
+    #[test]
+    fn test_wasm_fixture_abi() {
+        use crate::abi::CIRCUIT_ID;
+        use crate::host::{CompiledModule, HostConfig};
+
+        // Catches a checked-in module that predates the guest ABI, rebuild it with `make wasm`
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let compiled = CompiledModule::new(wasm_bytes, HostConfig::default()).unwrap();
+        assert_eq!(compiled.circuit_id().as_deref(), Some(CIRCUIT_ID));
+        let exports = compiled.exports();
+        for export in [
+            "alloc",
+            "dealloc",
+            "verify",
+            "verify_circuit",
+            "build_vk",
+            "load_params",
+            "load_vk",
+        ] {
+            assert!(
+                exports.iter().any(|name| name == export),
+                "module has no {} export",
+                export
+            );
+        }
+    }
+
+    #[test]
+    fn test_wasm_cost_function() {
+        use crate::host::{CostFunction, Harness, HostConfig};
+
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let mut gas_used = vec![];
+        for weight in [1, 2] {
+            let config = HostConfig {
+                cost_function: Some(CostFunction::new(move |_| weight)),
+                ..Default::default()
+            };
+            let mut harness = Harness::new(wasm_bytes, config).unwrap();
+            harness.call("entrypoint_no_verify_no_vk").unwrap();
+            gas_used.push(crate::host::DEFAULT_GAS_LIMIT - harness.remaining_gas().unwrap());
+        }
+        assert_eq!(gas_used[1], 2 * gas_used[0]);
+    }
+
+    #[test]
+    fn test_wasm_solana_compute_units() {
+        use crate::encode_instances;
+        use crate::host::{CostFunction, Harness, HostConfig, SOLANA_COMPUTE_UNIT_LIMIT};
+        use halo2_proofs::pasta::pallas;
+
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let config = HostConfig {
+            cost_function: Some(CostFunction::solana()),
+            ..Default::default()
+        };
+        let mut harness = Harness::new(wasm_bytes, config).unwrap();
+        harness.warmup().unwrap();
+
+        let proof = include_bytes!("../proof.bin");
+        let instances = encode_instances(&crate::expected_instances(
+            pallas::Base::from(69),
+            pallas::Base::from(42),
+        ));
+        assert!(harness.verify(proof, &instances).unwrap().is_valid());
+        let compute_units = crate::host::DEFAULT_GAS_LIMIT - harness.remaining_gas().unwrap();
+        // Even without keygen, one verification doesn't fit in a Solana transaction
+        assert!(compute_units > SOLANA_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[test]
+    fn test_wasm_memory_limit() {
+        use crate::host::{Harness, HostConfig, HostError};
+
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let config = HostConfig {
+            max_memory_pages: Some(1),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Harness::new(wasm_bytes, config),
+            Err(HostError::MemoryLimit { limit: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_wasm_pool() {
+        use crate::host::{HostConfig, VerifierPool};
+
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let pool = VerifierPool::new(wasm_bytes, 2, HostConfig::default()).unwrap();
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| pool.call("entrypoint").unwrap());
+            }
+        });
+    }
+
+    #[test]
+    fn test_wasm_pool_replaces_failed_instances() {
+        use crate::host::{HostConfig, HostError, VerifierPool};
+
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        assert!(matches!(
+            VerifierPool::new(wasm_bytes, 0, HostConfig::default()),
+            Err(HostError::Config(_))
+        ));
+
+        let config = HostConfig {
+            gas_limit: Some(1_000),
+            ..Default::default()
+        };
+        let pool = VerifierPool::new(wasm_bytes, 2, config).unwrap();
+        assert!(matches!(pool.call("entrypoint"), Err(HostError::OutOfGas)));
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            pool.with_instance(|_| -> Result<(), HostError> { panic!("job panicked") })
+        }));
+        assert!(panicked.is_err());
+
+        // Both instances were replaced rather than lost, or the inner checkout would block
+        // forever
+        pool.with_instance(|first| {
+            pool.with_instance(|second| Ok((first.memory_pages()?, second.memory_pages()?)))
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_wasm_verify_abi() {
+        use crate::encode_instances;
+        use crate::host::{Harness, HostConfig};
+        use halo2_proofs::pasta::pallas;
+
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let mut harness = Harness::new(wasm_bytes, HostConfig::default()).unwrap();
+
+        let proof = include_bytes!("../proof.bin");
+        let public_inputs =
+            crate::expected_instances(pallas::Base::from(69), pallas::Base::from(42));
+        let instances = encode_instances(&public_inputs);
+        assert!(harness.verify(proof, &instances).unwrap().is_valid());
+        let too_few = encode_instances(&public_inputs[1..]);
+        assert!(!harness.verify(proof, &too_few).unwrap().is_valid());
+
+        // The fixture module is built without `perf`, so it never marks its phases
+        let (verdict, report) = harness.verify_with_perf(proof, &instances).unwrap();
+        assert!(verdict.is_valid());
+        assert!(report.is_none());
+
+        // The vk is built by now, so another verification fits in the memory it has
+        let (verdict, memory) = harness.verify_with_memory(proof, &instances).unwrap();
+        assert!(verdict.is_valid());
+        assert_eq!(memory.grown(), 0);
+    }
+
+    #[test]
+    fn test_wasm_snapshot() {
+        use crate::host::{CompiledModule, HostConfig};
+
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let compiled = CompiledModule::new(wasm_bytes, HostConfig::default()).unwrap();
+        let mut warm = compiled.instantiate().unwrap();
+        warm.warmup().unwrap();
+
+        let snapshot = warm.snapshot().unwrap();
+        let restored = compiled.instantiate_from(&snapshot).unwrap();
+        assert_eq!(
+            restored.memory_pages().unwrap(),
+            warm.memory_pages().unwrap()
         );
+    }
 
-        let entrypoint = instance
-            .exports
-            .get_function("entrypoint_no_verify")
-            .unwrap();
+    #[test]
+    fn test_wasm_artifact_cache() {
+        use crate::host::{ArtifactCache, HostConfig};
 
-        let now = Instant::now();
-        let _answer = entrypoint.call(&mut store, &[]).unwrap().to_vec();
-        println!("Wasm built vk in [{:?}ms]", now.elapsed().as_millis());
+        let wasm_bytes = include_bytes!("../wasm_verifier_arithmetic.wasm");
+        let dir = std::env::temp_dir().join(format!("wv-artifacts-{}", std::process::id()));
+        let cache = ArtifactCache::new(&dir, u64::MAX);
+        let path = cache.path(wasm_bytes, &HostConfig::default()).unwrap();
 
-        let entrypoint = instance
-            .exports
-            .get_function("entrypoint_no_verify_no_vk")
+        cache
+            .get_or_compile(wasm_bytes, HostConfig::default())
+            .unwrap();
+        assert!(path.exists());
+        let mut harness = cache
+            .get_or_compile(wasm_bytes, HostConfig::default())
+            .unwrap()
+            .instantiate()
             .unwrap();
+        harness.call("entrypoint").unwrap();
 
-        let now = Instant::now();
-        let _answer = entrypoint.call(&mut store, &[]).unwrap().to_vec();
-        println!(
-            "Wasm runs without building vk in [{:?}ms]",
-            now.elapsed().as_millis()
-        );
+        // Anything left over exceeds a zero budget
+        ArtifactCache::new(&dir, 0).evict().unwrap();
+        assert!(!path.exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "aot")]
+    #[test]
+    fn test_wasm_precompiled() {
+        use crate::host::{CompiledModule, HostConfig};
+
+        let compiled = CompiledModule::precompiled(HostConfig::default()).unwrap();
+        compiled.instantiate().unwrap().call("entrypoint").unwrap();
     }
 }
@@ -0,0 +1,159 @@
+//! Heap usage of proving, to help pick k and circuit sizes for the machines doing it.
+//!
+//! Rust has no allocator statistics of its own, so this only reports anything when the
+//! binary installs `CountingAllocator` as its global allocator:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOC: wasm_verifier_arithmetic::memory::CountingAllocator =
+//!     wasm_verifier_arithmetic::memory::CountingAllocator;
+//! ```
+//!
+//! The counters are process-wide, so anything else allocating while a proof is made shows
+//! up in its report too. Phases are the ones from progress.rs.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use halo2_proofs::pasta::pallas;
+use rand::RngCore;
+
+use crate::{
+    progress::ProvePhase,
+    prover::{ArithWitness, Prover},
+    MyCircuit, Proof, Result,
+};
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting live, peak and total bytes
+pub struct CountingAllocator;
+
+fn grew(bytes: usize) {
+    ALLOCATED.fetch_add(bytes, Ordering::Relaxed);
+    let current = CURRENT.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+fn shrank(bytes: usize) {
+    CURRENT.fetch_sub(bytes, Ordering::Relaxed);
+}
+
+// SAFETY: every call is forwarded to `System` unchanged, the counting has no effect on it
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        shrank(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            match new_size >= layout.size() {
+                true => grew(new_size - layout.size()),
+                false => shrank(layout.size() - new_size),
+            }
+        }
+        new
+    }
+}
+
+/// False unless `CountingAllocator` is the global allocator, in which case reports are
+/// all zeros
+pub fn is_counting() -> bool {
+    ALLOCATED.load(Ordering::Relaxed) > 0
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PhaseMemory {
+    pub phase: ProvePhase,
+    /// Bytes allocated during the phase, whether or not they were freed again
+    pub allocated: usize,
+    /// Highest live heap during the phase
+    pub peak: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProveMemoryReport {
+    /// Live heap before proving started
+    pub baseline: usize,
+    /// Highest live heap while proving
+    pub peak: usize,
+    pub phases: Vec<PhaseMemory>,
+}
+
+impl core::fmt::Display for ProveMemoryReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "prove peak [{} bytes] baseline [{} bytes]",
+            self.peak, self.baseline
+        )?;
+        for phase in &self.phases {
+            write!(
+                f,
+                " {:?} [{} allocated, {} peak]",
+                phase.phase, phase.allocated, phase.peak
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Prover {
+    /// `prove`, also reporting heap usage overall and per phase. See the module docs for
+    /// the allocator this needs.
+    pub fn prove_with_memory_report(
+        &self,
+        witness: &ArithWitness,
+        instances: &[pallas::Base],
+        rng: impl RngCore,
+    ) -> Result<(Proof, ProveMemoryReport)> {
+        let mut report = ProveMemoryReport {
+            baseline: CURRENT.load(Ordering::Relaxed),
+            ..Default::default()
+        };
+        let mut current: Option<(ProvePhase, usize)> = None;
+
+        let circuit = MyCircuit::from(witness);
+        let proof =
+            Proof::create_with_progress(self.proving_key(), &[circuit], instances, rng, |phase| {
+                let allocated = ALLOCATED.load(Ordering::Relaxed);
+                if let Some((previous, started)) = current.take() {
+                    report.phases.push(PhaseMemory {
+                        phase: previous,
+                        allocated: allocated - started,
+                        peak: PEAK.load(Ordering::Relaxed),
+                    });
+                }
+                // Peak from here on covers the new phase only
+                PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+                if phase != ProvePhase::Done {
+                    current = Some((phase, allocated));
+                }
+            })?;
+
+        report.peak = report.phases.iter().map(|p| p.peak).max().unwrap_or(0);
+        Ok((proof, report))
+    }
+}
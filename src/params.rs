@@ -0,0 +1,128 @@
+//! Reading and writing commitment params with a versioned header, so they can be
+//! generated once and shared between processes and the wasm guest.
+//!
+//! Layout: magic `WVPR`, version byte, k as u32 LE, then halo2's own params encoding.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io,
+    path::Path,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use halo2_proofs::{pasta::vesta, poly::commitment::Params};
+
+const PARAMS_MAGIC: &[u8; 4] = b"WVPR";
+const PARAMS_VERSION: u8 = 1;
+
+pub fn write_to<W: io::Write>(
+    k: u32,
+    params: &Params<vesta::Affine>,
+    writer: &mut W,
+) -> io::Result<()> {
+    writer.write_all(PARAMS_MAGIC)?;
+    writer.write_all(&[PARAMS_VERSION])?;
+    writer.write_all(&k.to_le_bytes())?;
+    params.write(writer)
+}
+
+/// The k params were generated for, from their number of generators
+pub fn k_of(params: &Params<vesta::Affine>) -> u32 {
+    params.get_g().len().trailing_zeros()
+}
+
+/// Returns k along with the params
+pub fn read_from<R: io::Read>(reader: &mut R) -> io::Result<(u32, Params<vesta::Affine>)> {
+    let mut header = [0u8; 9];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != PARAMS_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a params file",
+        ));
+    }
+    if header[4] != PARAMS_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported params version {}", header[4]),
+        ));
+    }
+    let k = u32::from_le_bytes(header[5..9].try_into().unwrap());
+
+    let params = Params::read(reader)?;
+    if k_of(&params) != k {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "header says k = {}, the params are for k = {}",
+                k,
+                k_of(&params)
+            ),
+        ));
+    }
+    Ok((k, params))
+}
+
+pub fn to_bytes(k: u32, params: &Params<vesta::Affine>) -> Vec<u8> {
+    let mut bytes = vec![];
+    // Writing to a Vec can't fail
+    write_to(k, params, &mut bytes).unwrap();
+    bytes
+}
+
+pub fn from_bytes(mut bytes: &[u8]) -> io::Result<(u32, Params<vesta::Affine>)> {
+    read_from(&mut bytes)
+}
+
+pub fn write_file(
+    path: impl AsRef<Path>,
+    k: u32,
+    params: &Params<vesta::Affine>,
+) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(File::create(path)?);
+    write_to(k, params, &mut writer)?;
+    io::Write::flush(&mut writer)
+}
+
+pub fn read_file(path: impl AsRef<Path>) -> io::Result<(u32, Params<vesta::Affine>)> {
+    read_from(&mut io::BufReader::new(File::open(path)?))
+}
+
+type Slot = Arc<OnceLock<Arc<Params<vesta::Affine>>>>;
+
+/// Params of size `k`, generated once per process and shared by every key built on them.
+/// Each k has its own slot, so generating a large k doesn't hold up callers of other ones.
+pub fn shared(k: u32) -> Arc<Params<vesta::Affine>> {
+    static SHARED: OnceLock<Mutex<HashMap<u32, Slot>>> = OnceLock::new();
+    let slot = SHARED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .entry(k)
+        .or_default()
+        .clone();
+    slot.get_or_init(|| Arc::new(Params::new(k))).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_header_k() {
+        let params = Params::<vesta::Affine>::new(3);
+        let bytes = to_bytes(3, &params);
+        assert_eq!(from_bytes(&bytes).unwrap().0, 3);
+
+        let mut wrong_k = bytes.clone();
+        wrong_k[5..9].copy_from_slice(&4u32.to_le_bytes());
+        assert!(from_bytes(&wrong_k).is_err());
+    }
+
+    #[test]
+    fn test_shared_params() {
+        assert!(Arc::ptr_eq(&shared(3), &shared(3)));
+        assert_eq!(k_of(&shared(2)), 2);
+    }
+}
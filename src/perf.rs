@@ -0,0 +1,73 @@
+//! Phase markers for profiling verification inside the guest, behind the `perf` feature.
+//!
+//! `report.rs` times the phases natively, but the guest has no clock. Instead it calls the
+//! host import `env.wv_perf_mark` at each phase boundary and the host records the time
+//! and the gas left, see `host::Harness::verify_with_perf`. Natively the markers do
+//! nothing. Verification does no FFTs, so the phases are the same three as `VerifyReport`.
+
+use halo2_proofs::{
+    pasta::vesta,
+    plonk::{self, VerificationStrategy},
+    poly::commitment::{Guard, Params, MSM},
+};
+
+/// Verification is about to read the transcript
+pub const MARK_START: u32 = 0;
+/// The transcript is read and the constraints checked
+pub const MARK_TRANSCRIPT: u32 = 1;
+/// The multiopen argument has built the final MSM
+pub const MARK_OPENING: u32 = 2;
+/// The final MSM is evaluated
+pub const MARK_MSM: u32 = 3;
+
+#[cfg(target_arch = "wasm32")]
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn wv_perf_mark(phase: u32);
+}
+
+pub(crate) fn mark(phase: u32) {
+    // SAFETY: the import takes a plain integer and touches no guest memory
+    #[cfg(target_arch = "wasm32")]
+    unsafe {
+        wv_perf_mark(phase)
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = phase;
+}
+
+/// `SingleVerifier`, marking each phase boundary
+pub(crate) struct MarkingVerifier<'params> {
+    msm: MSM<'params, vesta::Affine>,
+}
+
+impl<'params> MarkingVerifier<'params> {
+    pub(crate) fn new(params: &'params Params<vesta::Affine>) -> Self {
+        MarkingVerifier {
+            msm: params.empty_msm(),
+        }
+    }
+}
+
+impl<'params> VerificationStrategy<'params, vesta::Affine> for MarkingVerifier<'params> {
+    type Output = ();
+
+    fn process(
+        self,
+        f: impl FnOnce(
+            MSM<'params, vesta::Affine>,
+        ) -> Result<Guard<'params, vesta::Affine>, plonk::Error>,
+    ) -> Result<Self::Output, plonk::Error> {
+        mark(MARK_TRANSCRIPT);
+        let guard = f(self.msm)?;
+        mark(MARK_OPENING);
+        let valid = guard.use_challenges().eval();
+        mark(MARK_MSM);
+
+        if valid {
+            Ok(())
+        } else {
+            Err(plonk::Error::ConstraintSystemFailure)
+        }
+    }
+}
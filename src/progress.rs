@@ -0,0 +1,114 @@
+//! Progress reporting for long proving jobs.
+//!
+//! halo2's `create_proof` has no hooks, so the phases are read off the transcript: the
+//! prover writes all its commitments, then all its evaluations, then the multiopen
+//! argument, which starts with a commitment again. Synthesis runs inside `create_proof`
+//! before the first commitment is written.
+
+use std::io;
+
+use halo2_proofs::{
+    pasta::{pallas, vesta},
+    plonk,
+    transcript::{Challenge255, Transcript, TranscriptWrite},
+};
+use rand::RngCore;
+
+use crate::{prover::ProvingKey, Proof};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProvePhase {
+    /// Witness generation and the first advice commitments
+    Synthesis,
+    /// Lookup, permutation and vanishing argument commitments
+    Commitments,
+    /// Evaluating every polynomial at the challenge point
+    Evaluations,
+    /// The multiopen argument
+    Opening,
+    Done,
+}
+
+/// Passes everything through to `inner`, calling `on_phase` when the writes move on to
+/// the next phase
+struct ProgressTranscript<'f, T> {
+    inner: T,
+    phase: ProvePhase,
+    on_phase: &'f mut dyn FnMut(ProvePhase),
+}
+
+impl<T> ProgressTranscript<'_, T> {
+    fn enter(&mut self, phase: ProvePhase) {
+        if self.phase != phase {
+            self.phase = phase;
+            (self.on_phase)(phase);
+        }
+    }
+}
+
+impl<T: Transcript<vesta::Affine, Challenge255<vesta::Affine>>>
+    Transcript<vesta::Affine, Challenge255<vesta::Affine>> for ProgressTranscript<'_, T>
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<vesta::Affine> {
+        self.inner.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: vesta::Affine) -> io::Result<()> {
+        self.inner.common_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: pallas::Base) -> io::Result<()> {
+        self.inner.common_scalar(scalar)
+    }
+}
+
+impl<T: TranscriptWrite<vesta::Affine, Challenge255<vesta::Affine>>>
+    TranscriptWrite<vesta::Affine, Challenge255<vesta::Affine>> for ProgressTranscript<'_, T>
+{
+    fn write_point(&mut self, point: vesta::Affine) -> io::Result<()> {
+        match self.phase {
+            ProvePhase::Synthesis => self.enter(ProvePhase::Commitments),
+            ProvePhase::Evaluations => self.enter(ProvePhase::Opening),
+            _ => {}
+        }
+        self.inner.write_point(point)
+    }
+
+    fn write_scalar(&mut self, scalar: pallas::Base) -> io::Result<()> {
+        if self.phase == ProvePhase::Commitments {
+            self.enter(ProvePhase::Evaluations);
+        }
+        self.inner.write_scalar(scalar)
+    }
+}
+
+impl Proof {
+    /// `create`, calling `on_phase` as proving enters each phase. `Synthesis` is reported
+    /// before any work starts and `Done` once the proof is complete.
+    pub fn create_with_progress(
+        pk: &ProvingKey,
+        circuits: &[impl plonk::Circuit<pallas::Base>],
+        instances: &[pallas::Base],
+        mut rng: impl RngCore,
+        mut on_phase: impl FnMut(ProvePhase),
+    ) -> Result<Self, plonk::Error> {
+        on_phase(ProvePhase::Synthesis);
+        let mut transcript = ProgressTranscript {
+            inner: crate::transcript::writer(vec![]),
+            phase: ProvePhase::Synthesis,
+            on_phase: &mut on_phase,
+        };
+        plonk::create_proof(
+            &pk.params,
+            &pk.pk,
+            circuits,
+            &[&[instances]],
+            &mut rng,
+            &mut transcript,
+        )?;
+
+        let proof = Proof::new(transcript.inner.finalize());
+        on_phase(ProvePhase::Done);
+        Ok(proof)
+    }
+}
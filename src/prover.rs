@@ -0,0 +1,426 @@
+//! Proof generation for the crate's circuits, behind the `prover` feature.
+//!
+//! Proving is single-threaded unless the `multicore` feature lets halo2 use rayon.
+
+use std::{io, sync::Arc};
+
+use halo2_proofs::{
+    circuit::Value,
+    pasta::{pallas, vesta},
+    plonk::{self, Circuit},
+    poly::commitment::Params,
+};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{
+    dry_run::ConstraintFailures,
+    progress::ProvePhase,
+    witness::{WitnessBuilder, WitnessError},
+    MyCircuit, Proof, Result, VerifyingKey,
+};
+
+const PK_MAGIC: &[u8; 4] = b"WVPK";
+const PK_VERSION: u8 = 1;
+
+#[derive(Clone, Debug)]
+pub struct ProvingKey {
+    pub k: u32,
+    pub params: Arc<Params<vesta::Affine>>,
+    pub pk: plonk::ProvingKey<vesta::Affine>,
+}
+
+impl ProvingKey {
+    pub fn build(k: u32, c: &impl Circuit<pallas::Base>) -> Result<Self> {
+        Self::build_with_params(k, crate::params::shared(k), c)
+    }
+
+    /// Like `build`, but with params of size `k` obtained elsewhere, e.g. read from a file
+    /// once for every circuit of that size
+    pub fn build_with_params(
+        k: u32,
+        params: Arc<Params<vesta::Affine>>,
+        c: &impl Circuit<pallas::Base>,
+    ) -> Result<Self> {
+        let vk = plonk::keygen_vk(&params, c)?;
+        let pk = plonk::keygen_pk(&params, vk, c)?;
+        Ok(ProvingKey { k, params, pk })
+    }
+
+    /// The matching verifying key, without running keygen again
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey::new(self.k, self.params.clone(), self.pk.get_vk().clone())
+    }
+
+    /// Serialized as magic, version, then the verifying key. halo2 has no proving key
+    /// encoding, so `read` re-derives the proving key from the vk with `keygen_pk`.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(PK_MAGIC)?;
+        writer.write_all(&[PK_VERSION])?;
+        self.verifying_key().write(writer)
+    }
+
+    pub fn read<R: io::Read, C: Circuit<pallas::Base>>(
+        reader: &mut R,
+        circuit: C,
+    ) -> io::Result<Self> {
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != PK_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a proving key",
+            ));
+        }
+        if header[4] != PK_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported proving key version {}", header[4]),
+            ));
+        }
+
+        let circuit = circuit.without_witnesses();
+        let vk = VerifyingKey::read(reader, circuit.without_witnesses())?;
+        let pk = plonk::keygen_pk(&vk.params, vk.vk, &circuit)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(ProvingKey {
+            k: vk.k,
+            params: vk.params,
+            pk,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        // Writing to a Vec can't fail
+        self.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    pub fn from_bytes<C: Circuit<pallas::Base>>(mut bytes: &[u8], circuit: C) -> io::Result<Self> {
+        Self::read(&mut bytes, circuit)
+    }
+}
+
+// I: An on-disk proving key cache. Dropped: halo2_proofs 0.3 has no proving key encoding,
+// so an entry could only hold the vk, as `ProvingKey::write` does, and a hit would still
+// run `keygen_pk`, the step the cache was for. The vk half is what `vk_cache` stores.
+// Within one process, keep the `ProvingKey` around instead.
+
+// I: GPU MSMs for proving. Not attempted: halo2_proofs 0.3 calls its own `best_multiexp`
+// from inside `create_proof` and the IPA commitment code, with no hook to swap in another
+// backend, so offloading means patching the halo2 fork rather than a feature here. The
+// GPU crates that exist (ec-gpu-gen and friends) also target BLS12-381 and BN254, not pasta.
+// `multicore` is the supported way to speed up bulk proving.
+impl Proof {
+    pub fn create(
+        pk: &ProvingKey,
+        circuits: &[impl Circuit<pallas::Base>],
+        instances: &[pallas::Base],
+        rng: impl RngCore,
+    ) -> std::result::Result<Self, plonk::Error> {
+        Ok(Proof::new(Self::create_into(
+            pk,
+            circuits,
+            instances,
+            rng,
+            vec![],
+        )?))
+    }
+
+    /// `create`, writing the proof to `writer` as it is produced instead of into a new
+    /// buffer. Pass `&mut Vec<u8>` to reuse one, or a file or socket to stream it.
+    /// Write errors come back as `plonk::Error::Transcript`, and leave a partial proof.
+    pub fn create_into<W: io::Write>(
+        pk: &ProvingKey,
+        circuits: &[impl Circuit<pallas::Base>],
+        instances: &[pallas::Base],
+        rng: impl RngCore,
+        writer: W,
+    ) -> std::result::Result<W, plonk::Error> {
+        write_proof(pk, circuits, &[&[instances]], rng, writer)
+    }
+
+    /// One proof for several assignments of the circuit, sharing one transcript and one
+    /// opening argument. `instances` holds the instance columns of each circuit in turn.
+    /// Check it with `Proof::verify_multi`.
+    pub fn create_multi(
+        pk: &ProvingKey,
+        circuits: &[impl Circuit<pallas::Base>],
+        instances: &[&[&[pallas::Base]]],
+        rng: impl RngCore,
+    ) -> std::result::Result<Self, plonk::Error> {
+        Ok(Proof::new(write_proof(
+            pk,
+            circuits,
+            instances,
+            rng,
+            vec![],
+        )?))
+    }
+}
+
+fn write_proof<W: io::Write>(
+    pk: &ProvingKey,
+    circuits: &[impl Circuit<pallas::Base>],
+    instances: &[&[&[pallas::Base]]],
+    mut rng: impl RngCore,
+    writer: W,
+) -> std::result::Result<W, plonk::Error> {
+    let mut transcript = crate::transcript::writer(writer);
+    plonk::create_proof(
+        &pk.params,
+        &pk.pk,
+        circuits,
+        instances,
+        &mut rng,
+        &mut transcript,
+    )?;
+
+    Ok(transcript.finalize())
+}
+
+/// The private inputs of the arithmetic circuit
+#[derive(Clone)]
+pub struct ArithWitness {
+    pub a: pallas::Base,
+    pub b: pallas::Base,
+}
+
+/// Redacted, the witness is secret
+impl core::fmt::Debug for ArithWitness {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ArithWitness").finish_non_exhaustive()
+    }
+}
+
+impl ArithWitness {
+    /// `a` then `b`, each a canonical field element
+    pub fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, WitnessError> {
+        let mut builder = WitnessBuilder::new(bytes);
+        let witness = ArithWitness {
+            a: builder.field()?,
+            b: builder.field()?,
+        };
+        builder.finish()?;
+        Ok(witness)
+    }
+
+    pub fn expected_instances(&self) -> Vec<pallas::Base> {
+        crate::expected_instances(self.a, self.b)
+    }
+}
+
+impl From<&ArithWitness> for MyCircuit {
+    fn from(witness: &ArithWitness) -> Self {
+        MyCircuit {
+            a: Value::known(witness.a),
+            b: Value::known(witness.b),
+        }
+    }
+}
+
+/// Proof generation for the arithmetic circuit
+pub struct Prover {
+    pk: ProvingKey,
+}
+
+impl Prover {
+    pub fn new(pk: ProvingKey) -> Self {
+        Prover { pk }
+    }
+
+    /// Run keygen for the arithmetic circuit at size `k`
+    pub fn build(k: u32) -> Result<Self> {
+        Ok(Self::new(ProvingKey::build(k, &MyCircuit::default())?))
+    }
+
+    pub fn build_with_params(k: u32, params: Arc<Params<vesta::Affine>>) -> Result<Self> {
+        Ok(Self::new(ProvingKey::build_with_params(
+            k,
+            params,
+            &MyCircuit::default(),
+        )?))
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey {
+        &self.pk
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.pk.verifying_key()
+    }
+
+    /// Check `witness` satisfies the circuit for `instances` without proving, naming every
+    /// failed constraint. Much faster than a proof that would fail to verify.
+    pub fn dry_run(
+        &self,
+        witness: &ArithWitness,
+        instances: &[pallas::Base],
+    ) -> std::result::Result<(), ConstraintFailures> {
+        crate::dry_run::dry_run(self.pk.k, &MyCircuit::from(witness), &[instances])
+    }
+
+    pub fn prove(
+        &self,
+        witness: &ArithWitness,
+        instances: &[pallas::Base],
+        rng: impl RngCore,
+    ) -> Result<Proof> {
+        let circuit = MyCircuit::from(witness);
+        Ok(Proof::create(&self.pk, &[circuit], instances, rng)?)
+    }
+
+    /// One proof that every `(witness, instances)` pair holds, see `Proof::create_multi`
+    pub fn prove_multi(
+        &self,
+        items: &[(&ArithWitness, &[pallas::Base])],
+        rng: impl RngCore,
+    ) -> Result<Proof> {
+        let circuits: Vec<_> = items
+            .iter()
+            .map(|(witness, _)| MyCircuit::from(*witness))
+            .collect();
+        let instances: Vec<[&[pallas::Base]; 1]> =
+            items.iter().map(|(_, instances)| [*instances]).collect();
+        let instances: Vec<&[&[pallas::Base]]> = instances.iter().map(|c| &c[..]).collect();
+        Ok(Proof::create_multi(&self.pk, &circuits, &instances, rng)?)
+    }
+
+    /// `prove`, writing the proof to `writer`, see `Proof::create_into`
+    pub fn prove_into<W: io::Write>(
+        &self,
+        witness: &ArithWitness,
+        instances: &[pallas::Base],
+        rng: impl RngCore,
+        writer: W,
+    ) -> Result<W> {
+        let circuit = MyCircuit::from(witness);
+        Ok(Proof::create_into(
+            &self.pk,
+            &[circuit],
+            instances,
+            rng,
+            writer,
+        )?)
+    }
+
+    /// One proof per `(witness, instances)` pair, all from this prover's key and params.
+    /// Proofs are made one after another: with `multicore` halo2 already uses every core
+    /// within each proof.
+    pub fn prove_many(
+        &self,
+        items: &[(&ArithWitness, &[pallas::Base])],
+        mut rng: impl RngCore,
+    ) -> Result<Vec<Proof>> {
+        items
+            .iter()
+            .map(|(witness, instances)| self.prove(witness, instances, &mut rng))
+            .collect()
+    }
+
+    /// `prove`, reporting each phase to `on_phase`, see progress.rs
+    pub fn prove_with_progress(
+        &self,
+        witness: &ArithWitness,
+        instances: &[pallas::Base],
+        rng: impl RngCore,
+        on_phase: impl FnMut(ProvePhase),
+    ) -> Result<Proof> {
+        let circuit = MyCircuit::from(witness);
+        Ok(Proof::create_with_progress(
+            &self.pk,
+            &[circuit],
+            instances,
+            rng,
+            on_phase,
+        )?)
+    }
+
+    /// Prove with the RNG seeded from `seed`, so the same inputs and seed always give the
+    /// same proof bytes. ChaCha20 rather than `StdRng`, whose algorithm may change between
+    /// rand versions. Only for fixtures and test vectors: a reused seed leaks the witness.
+    pub fn prove_deterministic(
+        &self,
+        witness: &ArithWitness,
+        instances: &[pallas::Base],
+        seed: [u8; 32],
+    ) -> Result<Proof> {
+        self.prove(witness, instances, ChaCha20Rng::from_seed(seed))
+    }
+}
+
+/// Overwrite a field element in a way the compiler can't elide
+fn zeroize_field(x: &mut pallas::Base) {
+    // SAFETY: `x` is a valid, aligned, exclusive reference
+    unsafe { core::ptr::write_volatile(x, pallas::Base::from(0)) };
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
+// Witnesses are the only secrets this crate holds, halo2's own copies inside
+// `create_proof` are out of our reach
+impl Zeroize for MyCircuit {
+    fn zeroize(&mut self) {
+        self.a.as_mut().map(zeroize_field);
+        self.b.as_mut().map(zeroize_field);
+    }
+}
+
+impl Drop for MyCircuit {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for MyCircuit {}
+
+impl Zeroize for ArithWitness {
+    fn zeroize(&mut self) {
+        zeroize_field(&mut self.a);
+        zeroize_field(&mut self.b);
+    }
+}
+
+impl Drop for ArithWitness {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for ArithWitness {}
+
+#[cfg(test)]
+mod tests {
+    use core::mem::ManuallyDrop;
+
+    use super::*;
+
+    #[test]
+    fn test_witness_zeroize() {
+        let zero = pallas::Base::from(0);
+        let witness = ArithWitness {
+            a: pallas::Base::from(69),
+            b: pallas::Base::from(42),
+        };
+        assert_eq!(format!("{:?}", witness), "ArithWitness { .. }");
+
+        let mut copy = witness.clone();
+        copy.zeroize();
+        assert_eq!((copy.a, copy.b), (zero, zero));
+
+        // Run the drop glue but keep the storage around to look at
+        let mut dropped = ManuallyDrop::new(witness);
+        unsafe { ManuallyDrop::drop(&mut dropped) };
+        assert_eq!(dropped.a, zero);
+        assert_eq!(dropped.b, zero);
+
+        let mut circuit = ManuallyDrop::new(MyCircuit {
+            a: Value::known(pallas::Base::from(69)),
+            b: Value::known(pallas::Base::from(42)),
+        });
+        unsafe { ManuallyDrop::drop(&mut circuit) };
+        circuit.a.assert_if_known(|a| *a == zero);
+        circuit.b.assert_if_known(|b| *b == zero);
+    }
+}
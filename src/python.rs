@@ -0,0 +1,150 @@
+//! Python bindings, behind the `python` feature, built with `maturin develop --features
+//! python` (add `prover` for `Prover`). They call the same `verify_bytes` the guest runs.
+//!
+//! Field elements cross as 32-byte little-endian canonical reprs, instance lists in the
+//! `encode_instances` encoding. Malformed input raises `ValueError`; a proof that
+//! doesn't verify is a `False` from `verify`, not an exception.
+
+use halo2_proofs::pasta::{group::ff::PrimeField, pallas};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+use crate::{abi::Verdict, verify_bytes, MyCircuit};
+
+fn value_error(e: impl core::fmt::Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn base(bytes: &[u8]) -> PyResult<pallas::Base> {
+    let repr: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| value_error("a field element is 32 bytes"))?;
+    Option::from(pallas::Base::from_repr(repr))
+        .ok_or_else(|| value_error("not a canonical field element"))
+}
+
+#[pyclass(name = "VerifyingKey")]
+struct PyVerifyingKey(crate::VerifyingKey);
+
+#[pymethods]
+impl PyVerifyingKey {
+    /// Keygen for the arithmetic circuit at size k
+    #[staticmethod]
+    fn build(k: u32) -> PyResult<Self> {
+        crate::VerifyingKey::build(k, &MyCircuit::default())
+            .map(PyVerifyingKey)
+            .map_err(value_error)
+    }
+
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        crate::VerifyingKey::from_bytes(bytes, MyCircuit::default())
+            .map(PyVerifyingKey)
+            .map_err(value_error)
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.0.to_bytes())
+    }
+
+    fn digest<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.0.digest())
+    }
+
+    fn to_json(&self) -> String {
+        self.0.to_json()
+    }
+
+    #[getter]
+    fn k(&self) -> u32 {
+        self.0.k
+    }
+
+    #[getter]
+    fn proof_size(&self) -> usize {
+        self.0.proof_size()
+    }
+
+    /// `instances` in the `encode_instances` encoding
+    fn verify(&self, proof: &[u8], instances: &[u8]) -> PyResult<bool> {
+        match Verdict::of(verify_bytes(&self.0, proof, instances)) {
+            Verdict::Valid => Ok(true),
+            Verdict::Invalid(_) => Ok(false),
+            Verdict::Malformed(msg) | Verdict::Error(msg) => Err(value_error(msg)),
+        }
+    }
+}
+
+#[cfg(feature = "prover")]
+#[pyclass(name = "Prover")]
+struct PyProver(crate::prover::Prover);
+
+#[cfg(feature = "prover")]
+#[pymethods]
+impl PyProver {
+    #[staticmethod]
+    fn build(k: u32) -> PyResult<Self> {
+        crate::prover::Prover::build(k)
+            .map(PyProver)
+            .map_err(value_error)
+    }
+
+    fn verifying_key(&self) -> PyVerifyingKey {
+        PyVerifyingKey(self.0.verifying_key())
+    }
+
+    /// A proof for the witness `(a, b)`, deterministic in `seed`
+    fn prove<'py>(
+        &self,
+        py: Python<'py>,
+        a: &[u8],
+        b: &[u8],
+        seed: [u8; 32],
+    ) -> PyResult<&'py PyBytes> {
+        let witness = crate::prover::ArithWitness {
+            a: base(a)?,
+            b: base(b)?,
+        };
+        let proof = self
+            .0
+            .prove_deterministic(&witness, &witness.expected_instances(), seed)
+            .map_err(value_error)?;
+        Ok(PyBytes::new(py, proof.as_ref()))
+    }
+}
+
+/// A list of 32-byte field elements in the `encode_instances` encoding
+#[pyfunction]
+fn encode_instances<'py>(py: Python<'py>, instances: Vec<&[u8]>) -> PyResult<&'py PyBytes> {
+    let instances = instances
+        .into_iter()
+        .map(base)
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(PyBytes::new(py, &crate::encode_instances(&instances)))
+}
+
+#[pyfunction]
+fn decode_instances<'py>(py: Python<'py>, bytes: &[u8]) -> PyResult<Vec<&'py PyBytes>> {
+    let instances = crate::decode_instances(bytes).map_err(value_error)?;
+    Ok(instances
+        .iter()
+        .map(|instance| PyBytes::new(py, &instance.to_repr()))
+        .collect())
+}
+
+/// The public inputs `(a + b, a * b, a - b)` for the witness `(a, b)`, encoded
+#[pyfunction]
+fn expected_instances<'py>(py: Python<'py>, a: &[u8], b: &[u8]) -> PyResult<&'py PyBytes> {
+    let instances = crate::expected_instances(base(a)?, base(b)?);
+    Ok(PyBytes::new(py, &crate::encode_instances(&instances)))
+}
+
+#[pymodule]
+fn wasm_verifier_arithmetic(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyVerifyingKey>()?;
+    #[cfg(feature = "prover")]
+    m.add_class::<PyProver>()?;
+    m.add_function(wrap_pyfunction!(encode_instances, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_instances, m)?)?;
+    m.add_function(wrap_pyfunction!(expected_instances, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,175 @@
+//! Row-usage accounting: how many rows a circuit's layout takes and the smallest k it fits.
+//!
+//! The circuit is laid out by its own floor planner against an `Assignment` that only
+//! records the highest row touched, so no values are needed and nothing is committed.
+
+use halo2_proofs::{
+    circuit::Value,
+    pasta::pallas,
+    plonk::{
+        self, Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Fixed,
+        FloorPlanner, Instance, Selector,
+    },
+};
+
+// I: Shrinking the arith layout to lower k. It takes 4 rows (both loads share row 0, then
+// one row per gate), and fusing the gates into one row wouldn't lower k: the instance
+// column alone holds 3 rows, and with the 6 reserved ones that is already more than a
+// k = 3 domain's 8.
+
+/// Rows a circuit's layout occupies, next to the rows halo2 reserves at the end of the
+/// domain for blinding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RowUsage {
+    /// One past the highest row assigned, enabled or copied to, instance rows included
+    pub rows: usize,
+    /// Blinding rows plus the one row halo2 keeps after them
+    pub reserved: usize,
+    /// The smallest domain keygen accepts for the constraint system, whatever the layout
+    pub minimum_rows: usize,
+}
+
+impl RowUsage {
+    /// The smallest k whose domain holds the layout and the reserved rows
+    pub fn minimal_k(&self) -> u32 {
+        let needed = (self.rows + self.reserved).max(self.minimum_rows);
+        needed.next_power_of_two().trailing_zeros()
+    }
+
+    /// Rows of a `2^k` domain left unused by the layout
+    pub fn spare_rows(&self, k: u32) -> usize {
+        (1usize << k).saturating_sub(self.rows + self.reserved)
+    }
+}
+
+/// Lay `circuit` out and count its rows
+pub fn row_usage<C: Circuit<pallas::Base>>(circuit: &C) -> Result<RowUsage, plonk::Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+    let mut counter = RowCounter::default();
+    C::FloorPlanner::synthesize(&mut counter, circuit, config, cs.constants().clone())?;
+    Ok(RowUsage {
+        rows: counter.rows,
+        reserved: cs.blinding_factors() + 1,
+        minimum_rows: cs.minimum_rows(),
+    })
+}
+
+#[derive(Default)]
+struct RowCounter {
+    rows: usize,
+}
+
+impl RowCounter {
+    fn touch(&mut self, row: usize) {
+        self.rows = self.rows.max(row + 1);
+    }
+}
+
+impl Assignment<pallas::Base> for RowCounter {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn exit_region(&mut self) {}
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), plonk::Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn query_instance(
+        &self,
+        _: Column<Instance>,
+        _: usize,
+    ) -> Result<Value<pallas::Base>, plonk::Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> Result<(), plonk::Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        row: usize,
+        _: V,
+    ) -> Result<(), plonk::Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        _: Column<Any>,
+        left_row: usize,
+        _: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), plonk::Error> {
+        self.touch(left_row);
+        self.touch(right_row);
+        Ok(())
+    }
+
+    // Only fills constants up to the end of the usable rows, which says nothing about
+    // the layout
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<pallas::Base>>,
+    ) -> Result<(), plonk::Error> {
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::MyCircuit;
+
+    #[test]
+    fn test_arith_row_usage() {
+        let usage = row_usage(&MyCircuit::default()).unwrap();
+        assert_eq!(usage.rows, 4);
+        assert_eq!(usage.minimal_k(), 4);
+        assert!(usage.spare_rows(4) > 0);
+    }
+}
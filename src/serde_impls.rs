@@ -0,0 +1,48 @@
+//! serde support behind the `serde` feature. Everything is encoded as bytes, reusing the
+//! crate's own binary formats.
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{decode_instances, encode_instances, Instances, MyCircuit, Proof, VerifyingKey};
+
+impl Serialize for Proof {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_ref())
+    }
+}
+
+impl<'de> Deserialize<'de> for Proof {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Proof::new(Vec::<u8>::deserialize(deserializer)?))
+    }
+}
+
+impl Serialize for VerifyingKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.to_bytes())
+    }
+}
+
+/// Rebuilds the constraint system of the crate's arithmetic circuit
+impl<'de> Deserialize<'de> for VerifyingKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        VerifyingKey::from_bytes(&bytes, MyCircuit::default()).map_err(D::Error::custom)
+    }
+}
+
+/// Encoded with `encode_instances`
+impl Serialize for Instances {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&encode_instances(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Instances {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        decode_instances(&bytes)
+            .map(Instances)
+            .map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,51 @@
+//! Solana program entrypoint, behind the `solana` feature and only for `target_os =
+//! "solana"`. `make sbf` builds it with `cargo build-sbf`.
+//!
+//! Instruction data is the proof length as u32 LE, the proof, then the instances in the
+//! `encode_instances` encoding. A valid proof returns Ok, anything else
+//! `ProgramError::Custom` with the `abi::STATUS_` code. No accounts are read.
+//!
+//! What this does not get around:
+//! - the program runs once per instruction, so the vk is rebuilt every time. Build with
+//!   `embedded_keys` to deserialize it instead of running keygen.
+//! - the default bump heap is 32 KiB. Verification needs more, so transactions have to
+//!   request a larger heap frame (up to 256 KiB) from the compute budget program, and
+//!   the fixture entrypoints have to be left out, `entrypoint` is the program's symbol.
+//! - verification is far beyond the 1.4M compute units a transaction may use. See
+//!   `host::CostFunction::solana` for the estimate, measured on the wasm guest.
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, msg,
+    program_error::ProgramError, pubkey::Pubkey,
+};
+
+use crate::abi::{verify_buffers, Verdict};
+
+entrypoint!(process_instruction);
+
+fn process_instruction(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if data.len() < 4 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (len, rest) = data.split_at(4);
+    let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+    if rest.len() < len {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (proof, instances) = rest.split_at(len);
+
+    solana_program::log::sol_log_compute_units();
+    let verdict = verify_buffers(proof, instances);
+    solana_program::log::sol_log_compute_units();
+    match verdict {
+        Verdict::Valid => Ok(()),
+        verdict => {
+            msg!("{:?}", verdict);
+            Err(ProgramError::Custom(verdict.status() as u32))
+        }
+    }
+}
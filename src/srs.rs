@@ -0,0 +1,124 @@
+//! Shared structured reference string (params) discovery.
+//!
+//! Instead of every process running `Params::new(k)`, operators install one params file per
+//! k (see `install`) and processes load it with `load`. The file is looked up in:
+//!
+//! 1. `$WASM_VERIFIER_SRS`, either the file itself or a directory holding it
+//! 2. `$XDG_DATA_HOME/wasm-verifier/`, or `~/.local/share/wasm-verifier/`
+//!
+//! under the name `params-k{k}.bin`, and is rejected unless its blake2b checksum matches
+//! the one computed at build time.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use halo2_proofs::{pasta::vesta, poly::commitment::Params};
+
+use crate::params;
+
+include!(concat!(env!("OUT_DIR"), "/srs_checksums.rs"));
+
+pub const SRS_ENV: &str = "WASM_VERIFIER_SRS";
+
+#[derive(Debug)]
+pub enum SrsError {
+    /// No params file for this k in any of the searched locations
+    NotFound(u32),
+    /// No checksum is embedded for this k
+    UnsupportedK(u32),
+    ChecksumMismatch(PathBuf),
+    Io(io::Error),
+}
+
+impl fmt::Display for SrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SrsError::NotFound(k) => write!(f, "no params file found for k = {}", k),
+            SrsError::UnsupportedK(k) => write!(f, "no known checksum for k = {}", k),
+            SrsError::ChecksumMismatch(path) => {
+                write!(f, "checksum mismatch for {}", path.display())
+            }
+            SrsError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SrsError {}
+
+impl From<io::Error> for SrsError {
+    fn from(e: io::Error) -> Self {
+        SrsError::Io(e)
+    }
+}
+
+pub fn file_name(k: u32) -> String {
+    format!("params-k{}.bin", k)
+}
+
+pub fn expected_checksum(k: u32) -> Option<[u8; 32]> {
+    EXPECTED_CHECKSUMS
+        .iter()
+        .find(|(known_k, _)| *known_k == k)
+        .map(|(_, checksum)| *checksum)
+}
+
+pub fn checksum(bytes: &[u8]) -> [u8; 32] {
+    let hash = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"WasmVerifierSRS_")
+        .hash(bytes);
+    hash.as_bytes().try_into().unwrap()
+}
+
+/// The standard directory for shared params
+pub fn default_dir() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_home.join("wasm-verifier"))
+}
+
+/// The first existing params file for `k`, in search order
+pub fn locate(k: u32) -> Option<PathBuf> {
+    let from_env = std::env::var_os(SRS_ENV).map(PathBuf::from).map(|path| {
+        if path.is_dir() {
+            path.join(file_name(k))
+        } else {
+            path
+        }
+    });
+    let from_default = default_dir().map(|dir| dir.join(file_name(k)));
+
+    from_env
+        .into_iter()
+        .chain(from_default)
+        .find(|path| path.is_file())
+}
+
+/// Locate, checksum and load the params for `k`
+pub fn load(k: u32) -> Result<Params<vesta::Affine>, SrsError> {
+    let expected = expected_checksum(k).ok_or(SrsError::UnsupportedK(k))?;
+    let path = locate(k).ok_or(SrsError::NotFound(k))?;
+
+    let bytes = std::fs::read(&path)?;
+    if checksum(&bytes) != expected {
+        return Err(SrsError::ChecksumMismatch(path));
+    }
+    // The checksum pins the whole file, k included
+    let (_, params) = params::from_bytes(&bytes)?;
+    Ok(params)
+}
+
+/// Generate the params for `k` and write them where `load` finds them, returning the path
+pub fn install(k: u32, dir: Option<&Path>) -> Result<PathBuf, SrsError> {
+    let dir = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => default_dir().ok_or(SrsError::NotFound(k))?,
+    };
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(file_name(k));
+    params::write_file(&path, k, &Params::new(k))?;
+    Ok(path)
+}
@@ -0,0 +1,107 @@
+//! A proof bundled with its public inputs and the id of the circuit it is for, which is
+//! the unit transaction formats carry.
+//!
+//! Layout: magic `WVST`, format version, circuit id (32 bytes), the instances encoded with
+//! `encode_instances`, then the transcript bytes.
+
+use halo2_proofs::{pasta::pallas, plonk};
+
+use crate::{decode_instances, encode_instances, vk_registry::VkRegistry, InstanceError, Proof};
+
+const STATEMENT_MAGIC: &[u8; 4] = b"WVST";
+pub const STATEMENT_VERSION: u8 = 1;
+
+#[derive(Debug)]
+pub enum StatementError {
+    Malformed(&'static str),
+    UnsupportedVersion(u8),
+    Instance(InstanceError),
+    /// No vk is registered under the statement's circuit id
+    UnknownCircuit([u8; 32]),
+    Verification(plonk::Error),
+}
+
+impl core::fmt::Display for StatementError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StatementError::Malformed(msg) => write!(f, "malformed statement: {}", msg),
+            StatementError::UnsupportedVersion(v) => {
+                write!(f, "unsupported statement version {}", v)
+            }
+            StatementError::Instance(e) => write!(f, "{}", e),
+            StatementError::UnknownCircuit(id) => {
+                write!(f, "no verifying key for circuit {}", crate::hex::encode(id))
+            }
+            StatementError::Verification(e) => write!(f, "verification failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StatementError {}
+
+impl From<InstanceError> for StatementError {
+    fn from(e: InstanceError) -> Self {
+        StatementError::Instance(e)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Statement {
+    pub circuit_id: [u8; 32],
+    /// The circuit's single instance column
+    pub instances: Vec<pallas::Base>,
+    pub proof: Proof,
+}
+
+impl Statement {
+    pub fn serialize(&self) -> Vec<u8> {
+        let instances = encode_instances(&self.instances);
+        let proof = self.proof.as_ref();
+        let mut bytes = Vec::with_capacity(4 + 1 + 32 + instances.len() + proof.len());
+        bytes.extend_from_slice(STATEMENT_MAGIC);
+        bytes.push(STATEMENT_VERSION);
+        bytes.extend_from_slice(&self.circuit_id);
+        bytes.extend_from_slice(&instances);
+        bytes.extend_from_slice(proof);
+        bytes
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, StatementError> {
+        if bytes.len() < 4 + 1 + 32 + 4 {
+            return Err(StatementError::Malformed("shorter than the header"));
+        }
+        if &bytes[..4] != STATEMENT_MAGIC {
+            return Err(StatementError::Malformed("bad magic"));
+        }
+        if bytes[4] != STATEMENT_VERSION {
+            return Err(StatementError::UnsupportedVersion(bytes[4]));
+        }
+        let circuit_id = bytes[5..37].try_into().unwrap();
+
+        // The instance count fixes where the transcript starts
+        let rest = &bytes[37..];
+        let count = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+        let instances_len = count
+            .checked_mul(32)
+            .and_then(|len| len.checked_add(4))
+            .filter(|len| *len <= rest.len())
+            .ok_or(StatementError::Instance(InstanceError::Truncated))?;
+        let (instances, proof) = rest.split_at(instances_len);
+
+        Ok(Statement {
+            circuit_id,
+            instances: decode_instances(instances)?,
+            proof: Proof::new(proof.to_vec()),
+        })
+    }
+
+    /// Look up the vk for the statement's circuit and verify the proof against it
+    pub fn verify(&self, registry: &VkRegistry) -> Result<(), StatementError> {
+        let vk = registry
+            .get(&self.circuit_id)
+            .ok_or(StatementError::UnknownCircuit(self.circuit_id))?;
+        self.proof
+            .verify(vk, &[&self.instances])
+            .map_err(StatementError::Verification)
+    }
+}
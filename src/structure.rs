@@ -0,0 +1,193 @@
+//! Cheap checks on a proof's and its instances' shape before any group operations.
+//!
+//! The transcript length of a proof is fixed by the vk's constraint system. Rather than
+//! re-deriving halo2's transcript layout here, `proof_size` runs the verifier once over a
+//! stream of filler bytes and counts how many it reads. The run fails its final check, but
+//! only after reading the whole transcript.
+//!
+//! `ProofShape` lists what that length follows from, for exploring how a circuit's layout
+//! trades against proof size.
+
+use std::io;
+
+use halo2_proofs::{
+    pasta::{group::GroupEncoding, pallas, vesta},
+    plonk::{self, SingleVerifier},
+};
+
+use crate::{Error, Proof, Result, VerifyingKey};
+
+/// Repeats one 32-byte chunk that decodes both as a curve point other than the identity
+/// (which the transcript rejects) and as a canonical scalar, counting what is read
+struct SizeProbe {
+    chunk: [u8; 32],
+    read: usize,
+}
+
+impl SizeProbe {
+    fn new() -> Self {
+        // The smallest x on the curve: a few bytes of x, far below either field modulus
+        let chunk = (1u64..)
+            .map(|x| {
+                let mut repr = [0u8; 32];
+                repr[..8].copy_from_slice(&x.to_le_bytes());
+                repr
+            })
+            .find(|repr| bool::from(vesta::Affine::from_bytes(repr).is_some()))
+            .unwrap();
+        SizeProbe { chunk, read: 0 }
+    }
+}
+
+impl io::Read for SizeProbe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        for byte in buf.iter_mut() {
+            *byte = self.chunk[self.read % 32];
+            self.read += 1;
+        }
+        Ok(buf.len())
+    }
+}
+
+impl VerifyingKey {
+    /// Length in bytes of every valid proof for this key. Computed on first use by one
+    /// verifier run, then cached.
+    pub fn proof_size(&self) -> usize {
+        *self.proof_size.get_or_init(|| {
+            let columns = vec![Vec::<pallas::Base>::new(); self.vk.cs().num_instance_columns()];
+            let columns: Vec<_> = columns.iter().map(Vec::as_slice).collect();
+
+            let mut probe = SizeProbe::new();
+            let strategy = SingleVerifier::new(&self.params);
+            let mut transcript = crate::transcript::reader(&mut probe);
+            // Expected to fail, the filler is no proof
+            let _ = plonk::verify_proof(
+                &self.params,
+                &self.vk,
+                strategy,
+                &[&columns],
+                &mut transcript,
+            );
+            probe.read
+        })
+    }
+}
+
+/// What a key's proof size follows from. halo2 0.3 fixes the rest: one multiopen
+/// strategy, and `blinding_factors` derived from the most queries on any advice column
+/// (at least 3, plus 2), so the knobs a circuit has are k, its columns and its rotations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofShape {
+    pub k: u32,
+    pub advice_columns: usize,
+    pub instance_columns: usize,
+    pub fixed_columns: usize,
+    /// Distinct (column, rotation) pairs, each adding an evaluation to the proof
+    pub advice_queries: usize,
+    pub lookups: usize,
+    /// Columns under the copy constraints, a permutation argument chunk per `degree - 2`
+    pub permutation_columns: usize,
+    pub degree: usize,
+    /// Rows at the end of the domain holding random values
+    pub blinding_factors: usize,
+    pub proof_bytes: usize,
+}
+
+impl core::fmt::Display for ProofShape {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "k [{}] advice [{} columns, {} queries] instance [{}] fixed [{}] lookups [{}] \
+             permutation [{} columns] degree [{}] blinding [{} rows] proof [{} bytes]",
+            self.k,
+            self.advice_columns,
+            self.advice_queries,
+            self.instance_columns,
+            self.fixed_columns,
+            self.lookups,
+            self.permutation_columns,
+            self.degree,
+            self.blinding_factors,
+            self.proof_bytes
+        )
+    }
+}
+
+impl VerifyingKey {
+    pub fn shape(&self) -> ProofShape {
+        let cs = self.vk.cs();
+        ProofShape {
+            k: self.k,
+            advice_columns: cs.num_advice_columns(),
+            instance_columns: cs.num_instance_columns(),
+            fixed_columns: cs.num_fixed_columns(),
+            advice_queries: cs.advice_queries().len(),
+            lookups: cs.lookups().len(),
+            permutation_columns: cs.permutation().get_columns().len(),
+            degree: cs.degree(),
+            blinding_factors: cs.blinding_factors(),
+            proof_bytes: self.proof_size(),
+        }
+    }
+}
+
+impl VerifyingKey {
+    /// Check the instances fit the circuit: one slice per instance column, each with the
+    /// recorded length if `with_instance_lengths` was set, or within the rows halo2 leaves
+    /// usable otherwise
+    pub fn check_instances(&self, instances: &[&[pallas::Base]]) -> Result<()> {
+        let cs = self.vk.cs();
+        if instances.len() != cs.num_instance_columns() {
+            return Err(Error::InstanceColumns {
+                expected: cs.num_instance_columns(),
+                found: instances.len(),
+            });
+        }
+
+        // Lengths recorded for another circuit, which has a different number of columns
+        if let Some(lengths) = &self.instance_lengths {
+            if lengths.len() != instances.len() {
+                return Err(Error::InstanceColumns {
+                    expected: lengths.len(),
+                    found: instances.len(),
+                });
+            }
+        }
+
+        let usable_rows = (1usize << self.k) - (cs.blinding_factors() + 1);
+        for (column, values) in instances.iter().enumerate() {
+            let expected = self
+                .instance_lengths
+                .as_ref()
+                .and_then(|lengths| lengths.get(column).copied());
+            let fits = match expected {
+                Some(expected) => values.len() == expected,
+                None => values.len() <= usable_rows,
+            };
+            if !fits {
+                return Err(Error::InstanceCount {
+                    column,
+                    expected: expected.unwrap_or(usable_rows),
+                    found: values.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Proof {
+    /// Reject a proof whose length can't match `vk` without doing any curve arithmetic, as
+    /// a filter in front of `verify`
+    pub fn validate_structure(&self, vk: &VerifyingKey) -> Result<()> {
+        Self::validate_len(self.as_ref().len(), vk)
+    }
+
+    pub(crate) fn validate_len(found: usize, vk: &VerifyingKey) -> Result<()> {
+        let expected = vk.proof_size();
+        if found != expected {
+            return Err(Error::ProofSize { expected, found });
+        }
+        Ok(())
+    }
+}
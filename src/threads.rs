@@ -0,0 +1,49 @@
+//! Bounding the cores native keygen and verification use.
+//!
+//! `verify_many` and halo2's `multicore` FFTs and MSMs run on whatever rayon pool is
+//! current, the global one by default, which takes every core. `set_global_threads` caps
+//! that pool for the whole process; a `Threads` pool confines only the work run in it.
+
+use halo2_proofs::{pasta::pallas, plonk};
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::{Proof, VerifyingKey};
+
+/// Size rayon's global pool to `threads`. Fails if the pool already exists, which
+/// happens on the first parallel call, so call it at startup.
+pub fn set_global_threads(threads: usize) -> Result<(), ThreadPoolBuildError> {
+    ThreadPoolBuilder::new().num_threads(threads).build_global()
+}
+
+/// A dedicated pool, e.g. to keep verification off the cores a node uses for networking
+pub struct Threads {
+    pool: ThreadPool,
+}
+
+impl Threads {
+    pub fn new(threads: usize) -> Result<Self, ThreadPoolBuildError> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("wasm-verifier-{}", i))
+            .build()?;
+        Ok(Threads { pool })
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Run `f` in this pool. Keygen and proving called from `f` spread over it too.
+    pub fn install<T: Send>(&self, f: impl FnOnce() -> T + Send) -> T {
+        self.pool.install(f)
+    }
+
+    /// `verify_many` within this pool
+    pub fn verify_many(
+        &self,
+        vk: &VerifyingKey,
+        items: &[(&Proof, &[&[pallas::Base]])],
+    ) -> Vec<Result<(), plonk::Error>> {
+        self.install(|| crate::verify_many(vk, items))
+    }
+}
@@ -0,0 +1,187 @@
+//! The transcript every proof in this crate is read from and written to.
+//!
+//! Blake2b by default. The `cheap_transcript` feature swaps it for a few multiplies per
+//! word, so that benchmarks can tell how much of verification is hashing and how much is
+//! group arithmetic. That transcript offers no soundness at all, its proofs don't verify
+//! in default builds and vice versa, and halo2's own `BatchVerifier` (the `batch` feature)
+//! still uses Blake2b. Never use it for anything but measurements.
+
+#[cfg(not(feature = "cheap_transcript"))]
+mod imp {
+    use std::io;
+
+    use halo2_proofs::{
+        pasta::vesta,
+        transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+    };
+
+    pub(crate) type Reader<R> = Blake2bRead<R, vesta::Affine, Challenge255<vesta::Affine>>;
+    pub(crate) type Writer<W> = Blake2bWrite<W, vesta::Affine, Challenge255<vesta::Affine>>;
+
+    pub(crate) fn reader<R: io::Read>(reader: R) -> Reader<R> {
+        Blake2bRead::init(reader)
+    }
+
+    pub(crate) fn writer<W: io::Write>(writer: W) -> Writer<W> {
+        Blake2bWrite::init(writer)
+    }
+}
+
+#[cfg(feature = "cheap_transcript")]
+mod imp {
+    use std::io;
+
+    use halo2_proofs::{
+        pasta::{
+            group::{ff::PrimeField, GroupEncoding},
+            pallas, vesta,
+        },
+        transcript::{Challenge255, EncodedChallenge, Transcript, TranscriptRead, TranscriptWrite},
+    };
+
+    /// splitmix64's finalizer
+    fn mix(mut x: u64) -> u64 {
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^ (x >> 31)
+    }
+
+    #[derive(Clone, Default)]
+    struct CheapHash {
+        state: [u64; 8],
+        words: usize,
+    }
+
+    impl CheapHash {
+        fn absorb(&mut self, bytes: &[u8]) {
+            for chunk in bytes.chunks(8) {
+                let mut word = [0u8; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                let i = self.words % 8;
+                self.state[i] = mix(self.state[i] ^ u64::from_le_bytes(word));
+                self.words += 1;
+            }
+        }
+
+        /// Like Blake2bRead, the output depends on everything absorbed so far, and
+        /// squeezing again without absorbing gives a fresh challenge
+        fn squeeze(&mut self) -> Challenge255<vesta::Affine> {
+            self.absorb(&[0xff]);
+            let mut out = [0u8; 64];
+            for (i, bytes) in out.chunks_mut(8).enumerate() {
+                let word = mix(self.state[i] ^ self.state[(i + 1) % 8] ^ i as u64);
+                bytes.copy_from_slice(&word.to_le_bytes());
+            }
+            Challenge255::new(&out)
+        }
+    }
+
+    pub(crate) struct Reader<R> {
+        reader: R,
+        hash: CheapHash,
+    }
+
+    pub(crate) struct Writer<W> {
+        writer: W,
+        hash: CheapHash,
+    }
+
+    pub(crate) fn reader<R: io::Read>(reader: R) -> Reader<R> {
+        Reader {
+            reader,
+            hash: CheapHash::default(),
+        }
+    }
+
+    pub(crate) fn writer<W: io::Write>(writer: W) -> Writer<W> {
+        Writer {
+            writer,
+            hash: CheapHash::default(),
+        }
+    }
+
+    impl<W> Writer<W> {
+        pub(crate) fn finalize(self) -> W {
+            self.writer
+        }
+    }
+
+    impl<R: io::Read> Transcript<vesta::Affine, Challenge255<vesta::Affine>> for Reader<R> {
+        fn squeeze_challenge(&mut self) -> Challenge255<vesta::Affine> {
+            self.hash.squeeze()
+        }
+
+        fn common_point(&mut self, point: vesta::Affine) -> io::Result<()> {
+            self.hash.absorb(&point.to_bytes());
+            Ok(())
+        }
+
+        fn common_scalar(&mut self, scalar: pallas::Base) -> io::Result<()> {
+            self.hash.absorb(&scalar.to_repr());
+            Ok(())
+        }
+    }
+
+    impl<R: io::Read> TranscriptRead<vesta::Affine, Challenge255<vesta::Affine>> for Reader<R> {
+        fn read_point(&mut self) -> io::Result<vesta::Affine> {
+            let mut repr = [0u8; 32];
+            self.reader.read_exact(&mut repr)?;
+            let point: Option<_> = vesta::Affine::from_bytes(&repr).into();
+            let point = point.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof")
+            })?;
+            self.common_point(point)?;
+            Ok(point)
+        }
+
+        fn read_scalar(&mut self) -> io::Result<pallas::Base> {
+            let mut repr = [0u8; 32];
+            self.reader.read_exact(&mut repr)?;
+            let scalar: Option<_> = pallas::Base::from_repr(repr).into();
+            let scalar = scalar.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    "invalid field element encoding in proof",
+                )
+            })?;
+            self.common_scalar(scalar)?;
+            Ok(scalar)
+        }
+    }
+
+    impl<W: io::Write> Transcript<vesta::Affine, Challenge255<vesta::Affine>> for Writer<W> {
+        fn squeeze_challenge(&mut self) -> Challenge255<vesta::Affine> {
+            self.hash.squeeze()
+        }
+
+        fn common_point(&mut self, point: vesta::Affine) -> io::Result<()> {
+            self.hash.absorb(&point.to_bytes());
+            Ok(())
+        }
+
+        fn common_scalar(&mut self, scalar: pallas::Base) -> io::Result<()> {
+            self.hash.absorb(&scalar.to_repr());
+            Ok(())
+        }
+    }
+
+    impl<W: io::Write> TranscriptWrite<vesta::Affine, Challenge255<vesta::Affine>> for Writer<W> {
+        fn write_point(&mut self, point: vesta::Affine) -> io::Result<()> {
+            self.common_point(point)?;
+            self.writer.write_all(&point.to_bytes())
+        }
+
+        fn write_scalar(&mut self, scalar: pallas::Base) -> io::Result<()> {
+            self.common_scalar(scalar)?;
+            self.writer.write_all(&scalar.to_repr())
+        }
+    }
+}
+
+pub(crate) use imp::{reader, writer, Reader, Writer};
+
+// I: Batched point decompression. Nothing to share: a compressed pasta point is x and a
+// sign bit, and decompressing it is a square root of x^3 + 5, with no inversion a batch
+// could amortize (the points are affine throughout). The verifier also hashes each point
+// into the transcript as soon as it reads it, so even a batch of square roots would
+// need every point's offset ahead of time, i.e. a second copy of halo2's layout.
@@ -0,0 +1,133 @@
+//! On-disk cache of built verifying keys, so keygen runs once per circuit rather than once
+//! per process.
+//!
+//! Entries are named `{cache key}.vk` and hold the vk in halo2's bare encoding; params
+//! come from `params::shared` on load rather than being stored with every key. The cache
+//! key (`cache_key`) hashes the caller's name for the circuit with this crate's version,
+//! the constraint system and k. What `synthesize` assigns to fixed columns can't be seen
+//! without keygen, so a circuit whose layout changes between releases needs a new name.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use halo2_proofs::{
+    pasta::pallas,
+    plonk::{Circuit, ConstraintSystem},
+};
+
+use crate::{hex, params, Result, VerifyingKey};
+
+/// blake2b-256 of this crate's version, `circuit_id`, the circuit's pinned constraint
+/// system and k, computed without keygen. The constraint system goes in through its
+/// `Debug` output, which halo2 may change between releases; that only costs a miss.
+pub fn cache_key<C: Circuit<pallas::Base>>(circuit_id: &str, k: u32) -> [u8; 32] {
+    versioned_key::<C>(env!("CARGO_PKG_VERSION"), circuit_id, k)
+}
+
+fn versioned_key<C: Circuit<pallas::Base>>(version: &str, circuit_id: &str, k: u32) -> [u8; 32] {
+    let mut cs = ConstraintSystem::default();
+    C::configure(&mut cs);
+    let mut state = blake2b_simd::Params::new()
+        .hash_length(32)
+        .personal(b"WasmVerifierCS__")
+        .to_state();
+    for part in [version, circuit_id] {
+        state.update(&(part.len() as u64).to_le_bytes());
+        state.update(part.as_bytes());
+    }
+    state.update(&k.to_le_bytes());
+    state.update(format!("{:?}", cs.pinned()).as_bytes());
+    state.finalize().as_bytes().try_into().unwrap()
+}
+
+#[derive(Clone, Debug)]
+pub struct VkCache {
+    dir: PathBuf,
+    circuit_id: String,
+}
+
+impl VkCache {
+    /// A cache in `dir` for the circuit named `circuit_id`, e.g. `abi::CIRCUIT_ID`
+    pub fn new(dir: impl Into<PathBuf>, circuit_id: impl Into<String>) -> Self {
+        VkCache {
+            dir: dir.into(),
+            circuit_id: circuit_id.into(),
+        }
+    }
+
+    /// `$XDG_CACHE_HOME/wasm-verifier/vks`, or `~/.cache/wasm-verifier/vks`
+    pub fn default_dir() -> Option<PathBuf> {
+        let cache_home = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+        Some(cache_home.join("wasm-verifier").join("vks"))
+    }
+
+    pub fn path<C: Circuit<pallas::Base>>(&self, k: u32) -> PathBuf {
+        let key = cache_key::<C>(&self.circuit_id, k);
+        self.dir.join(format!("{}.vk", hex::encode(&key)))
+    }
+
+    /// The cached vk, or None if there is no entry or it doesn't parse
+    pub fn get<C: Circuit<pallas::Base>>(&self, k: u32, circuit: &C) -> Option<VerifyingKey> {
+        let bytes = fs::read(self.path::<C>(k)).ok()?;
+        VerifyingKey::from_halo2_bytes(&bytes, params::shared(k), circuit.without_witnesses()).ok()
+    }
+
+    pub fn insert<C: Circuit<pallas::Base>>(&self, vk: &VerifyingKey) -> io::Result<()> {
+        let mut bytes = vec![];
+        vk.vk.write(&mut bytes)?;
+        fs::create_dir_all(&self.dir)?;
+
+        // Write then rename, so a concurrent reader never sees a partial entry
+        let path = self.path::<C>(vk.k);
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, bytes)?;
+        fs::rename(tmp, path)
+    }
+
+    /// The cached vk, or one built with `VerifyingKey::build` and stored for next time.
+    /// Failing to store it is not an error, the key is still returned.
+    pub fn get_or_build<C: Circuit<pallas::Base>>(
+        &self,
+        k: u32,
+        circuit: &C,
+    ) -> Result<VerifyingKey> {
+        if let Some(vk) = self.get(k, circuit) {
+            return Ok(vk);
+        }
+        let vk = VerifyingKey::build(k, circuit)?;
+        let _ = self.insert::<C>(&vk);
+        Ok(vk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MyCircuit;
+
+    #[test]
+    fn test_vk_cache() {
+        let dir = std::env::temp_dir().join(format!("wv-vk-cache-{}", std::process::id()));
+        let cache = VkCache::new(&dir, "arith");
+        assert!(cache.get(4, &MyCircuit::default()).is_none());
+
+        let built = cache.get_or_build(4, &MyCircuit::default()).unwrap();
+        let cached = cache.get(4, &MyCircuit::default()).unwrap();
+        assert_eq!(cached.digest(), built.digest());
+        assert!(cache.get(5, &MyCircuit::default()).is_none());
+
+        // Renaming the circuit or releasing a new crate version invalidates the entry
+        let renamed = VkCache::new(&dir, "arith-v2");
+        assert!(renamed.get(4, &MyCircuit::default()).is_none());
+        assert_ne!(
+            versioned_key::<MyCircuit>("0.0.0", "arith", 4),
+            cache_key::<MyCircuit>("arith", 4)
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
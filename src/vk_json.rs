@@ -0,0 +1,46 @@
+//! Structured JSON export of a verifying key, for auditors and tooling outside of Rust that
+//! need to pin and review what the verifier checks against.
+//!
+//! Points are hex of their compressed encoding, field elements hex of their canonical repr.
+//! Every value is hex or a number, so no string escaping is needed.
+
+use halo2_proofs::pasta::{
+    group::{ff::PrimeField, GroupEncoding},
+    vesta,
+};
+
+use crate::{hex, VerifyingKey};
+
+fn points(points: &[vesta::Affine]) -> String {
+    let points: Vec<String> = points
+        .iter()
+        .map(|p| format!("\"{}\"", hex::encode(p.to_bytes().as_ref())))
+        .collect();
+    format!("[{}]", points.join(","))
+}
+
+impl VerifyingKey {
+    /// k, digest, evaluation domain, column counts and the fixed and permutation commitments
+    pub fn to_json(&self) -> String {
+        let domain = self.vk.get_domain();
+        let cs = self.vk.cs();
+        format!(
+            concat!(
+                "{{\"k\":{},\"digest\":\"{}\",",
+                "\"domain\":{{\"k\":{},\"extended_k\":{},\"omega\":\"{}\"}},",
+                "\"columns\":{{\"fixed\":{},\"advice\":{},\"instance\":{}}},",
+                "\"fixed_commitments\":{},\"permutation_commitments\":{}}}"
+            ),
+            self.k,
+            hex::encode(&self.digest()),
+            domain.k(),
+            domain.extended_k(),
+            hex::encode(domain.get_omega().to_repr().as_ref()),
+            cs.num_fixed_columns(),
+            cs.num_advice_columns(),
+            cs.num_instance_columns(),
+            points(self.vk.fixed_commitments()),
+            points(self.vk.permutation().commitments()),
+        )
+    }
+}
@@ -0,0 +1,32 @@
+//! Verifying keys indexed by circuit id (`VerifyingKey::digest`), for callers that verify
+//! proofs for more than one circuit natively.
+
+use std::collections::HashMap;
+
+use crate::VerifyingKey;
+
+#[derive(Clone, Debug, Default)]
+pub struct VkRegistry {
+    keys: HashMap<[u8; 32], VerifyingKey>,
+}
+
+impl VkRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the circuit id the key is registered under
+    pub fn insert(&mut self, vk: VerifyingKey) -> [u8; 32] {
+        let circuit_id = vk.digest();
+        self.keys.insert(circuit_id, vk);
+        circuit_id
+    }
+
+    pub fn get(&self, circuit_id: &[u8; 32]) -> Option<&VerifyingKey> {
+        self.keys.get(circuit_id)
+    }
+
+    pub fn circuit_ids(&self) -> impl Iterator<Item = &[u8; 32]> {
+        self.keys.keys()
+    }
+}
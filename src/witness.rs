@@ -0,0 +1,116 @@
+//! Reading circuit witnesses out of bytes, validating each value on the way, so the prover
+//! API and the guest ABI accept exactly the same encodings.
+//!
+//! Values are read in order: field elements as their 32-byte little-endian repr, integers
+//! as little-endian u64.
+
+use halo2_proofs::pasta::{group::ff::PrimeField, pallas};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WitnessError {
+    /// Ran out of bytes reading the value at this index
+    Truncated(usize),
+    /// The value at this index is not below the field modulus
+    NonCanonical(usize),
+    /// The integer at this index doesn't fit in the requested number of bits
+    OutOfRange {
+        index: usize,
+        bits: u32,
+    },
+    TrailingBytes,
+}
+
+impl core::fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WitnessError::Truncated(i) => write!(f, "witness {} is truncated", i),
+            WitnessError::NonCanonical(i) => {
+                write!(f, "witness {} is not a canonical field element", i)
+            }
+            WitnessError::OutOfRange { index, bits } => {
+                write!(f, "witness {} does not fit in {} bits", index, bits)
+            }
+            WitnessError::TrailingBytes => write!(f, "witness encoding has trailing bytes"),
+        }
+    }
+}
+
+impl std::error::Error for WitnessError {}
+
+pub struct WitnessBuilder<'a> {
+    bytes: &'a [u8],
+    index: usize,
+}
+
+impl<'a> WitnessBuilder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        WitnessBuilder { bytes, index: 0 }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], WitnessError> {
+        let (value, rest) = self
+            .bytes
+            .split_first_chunk::<N>()
+            .ok_or(WitnessError::Truncated(self.index))?;
+        self.bytes = rest;
+        self.index += 1;
+        Ok(*value)
+    }
+
+    pub fn field(&mut self) -> Result<pallas::Base, WitnessError> {
+        let index = self.index;
+        let repr = self.take::<32>()?;
+        Option::from(pallas::Base::from_repr(repr)).ok_or(WitnessError::NonCanonical(index))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, WitnessError> {
+        Ok(u64::from_le_bytes(self.take::<8>()?))
+    }
+
+    /// An integer below `2^bits` as a field element, for witnesses a circuit range checks
+    pub fn ranged(&mut self, bits: u32) -> Result<pallas::Base, WitnessError> {
+        let index = self.index;
+        let value = self.u64()?;
+        if bits < 64 && value >> bits != 0 {
+            return Err(WitnessError::OutOfRange { index, bits });
+        }
+        Ok(pallas::Base::from(value))
+    }
+
+    /// Fails if any bytes are left
+    pub fn finish(self) -> Result<(), WitnessError> {
+        match self.bytes.is_empty() {
+            true => Ok(()),
+            false => Err(WitnessError::TrailingBytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_witness_builder() {
+        let mut bytes = pallas::Base::from(69).to_repr().to_vec();
+        bytes.extend_from_slice(&300u64.to_le_bytes());
+
+        let mut builder = WitnessBuilder::new(&bytes);
+        assert_eq!(builder.field(), Ok(pallas::Base::from(69)));
+        assert_eq!(builder.ranged(16), Ok(pallas::Base::from(300)));
+        assert_eq!(builder.finish(), Ok(()));
+
+        let mut builder = WitnessBuilder::new(&bytes[32..]);
+        assert_eq!(
+            builder.ranged(8),
+            Err(WitnessError::OutOfRange { index: 0, bits: 8 })
+        );
+
+        let mut builder = WitnessBuilder::new(&[0xff; 32]);
+        assert_eq!(builder.field(), Err(WitnessError::NonCanonical(0)));
+        assert_eq!(
+            WitnessBuilder::new(&[0; 4]).u64(),
+            Err(WitnessError::Truncated(0))
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! `memory::CountingAllocator` installed as the global allocator. In the lib's own test
+//! binary it would count every other test's allocations too.
+//!
+//! cargo test --features prover --test memory
+
+use halo2_proofs::pasta::pallas;
+use rand::rngs::OsRng;
+
+use wasm_verifier_arithmetic::{
+    memory::{is_counting, CountingAllocator},
+    prover::{ArithWitness, Prover},
+};
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_prove_memory_report() {
+    let prover = Prover::build(4).unwrap();
+    let witness = ArithWitness {
+        a: pallas::Base::from(69),
+        b: pallas::Base::from(42),
+    };
+    let public_inputs = witness.expected_instances();
+
+    let (proof, report) = prover
+        .prove_with_memory_report(&witness, &public_inputs, OsRng)
+        .unwrap();
+    assert!(proof
+        .verify(&prover.verifying_key(), &[&public_inputs])
+        .is_ok());
+    assert!(is_counting());
+    assert_eq!(report.phases.len(), 4);
+    assert!(report.peak >= report.baseline);
+}